@@ -0,0 +1,84 @@
+// src/bin/generate-level.rs
+
+extern crate platform_rs;
+extern crate rand;
+
+use platform_rs::level::generate::{generate, Params};
+use platform_rs::level::tileset::{Tileset, DEFAULT_TILESET};
+
+use std::io::{self, Write};
+
+const USAGE: &'static str = "\
+Usage: generate-level [options]
+
+Options:
+    --seed N              seed of the level, random by default
+    --width N             width of the level in tiles
+    --height N            height of the level in tiles
+    --difficulty X        from 0, easy, to 1, hard
+    --gems N              number of gems
+    --tileset PATH        tileset of the level
+    -o, --output PATH     write the level to PATH instead of the standard output
+    -h, --help            show this message";
+
+fn main() {
+    let mut params = Params::default();
+    let mut seed: Option<u32> = None;
+    let mut tileset = DEFAULT_TILESET.to_string();
+    let mut output: Option<String> = None;
+
+    let mut args = ::std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let result = match arg.as_str() {
+            "-h" | "--help" => usage(""),
+            "--seed" => value(&mut args, &arg).map(|v| seed = Some(v)),
+            "--width" => value(&mut args, &arg).map(|v| params.width = v),
+            "--height" => value(&mut args, &arg).map(|v| params.height = v),
+            "--difficulty" => value(&mut args, &arg).map(|v| params.difficulty = v),
+            "--gems" => value(&mut args, &arg).map(|v| params.gems = v),
+            "--tileset" => value(&mut args, &arg).map(|v| tileset = v),
+            "-o" | "--output" => value(&mut args, &arg).map(|v| output = Some(v)),
+            _ => Err(format!("Unknown option '{}'", arg)),
+        };
+        if let Err(e) = result {
+            usage(&e);
+        }
+    }
+
+    let seed = seed.unwrap_or_else(rand::random::<u32>);
+
+    let file = Tileset::load(&tileset)
+        .map_err(|e| e.to_string())
+        .and_then(|tileset| generate(seed, &params, &tileset))
+        .unwrap_or_else(|e| {
+            eprintln!("generate-level: {}", e);
+            ::std::process::exit(1);
+        });
+
+    let result = match output {
+        Some(ref path) => file.save(path),
+        None => {
+            let stdout = io::stdout();
+            let mut out = stdout.lock();
+            file.write(&mut out).and_then(|_| out.flush())
+        }
+    };
+    if let Err(e) = result {
+        eprintln!("generate-level: {}", e);
+        ::std::process::exit(1);
+    }
+}
+
+fn value<I, T>(args: &mut I, option: &str) -> Result<T, String>
+    where I: Iterator<Item=String>, T: ::std::str::FromStr {
+    let value = args.next().ok_or_else(|| format!("Option '{}' requires a value", option))?;
+    value.parse::<T>().map_err(|_| format!("Invalid value '{}' for '{}'", value, option))
+}
+
+fn usage(error: &str) -> ! {
+    if !error.is_empty() {
+        eprintln!("{}\n", error);
+    }
+    eprintln!("{}", USAGE);
+    ::std::process::exit(2);
+}
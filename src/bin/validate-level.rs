@@ -0,0 +1,34 @@
+// src/bin/validate-level.rs
+
+extern crate platform_rs;
+
+use platform_rs::level::validate::{validate_path, Severity};
+
+const USAGE: &'static str = "Usage: validate-level LEVEL...";
+
+fn main() {
+    let paths: Vec<String> = ::std::env::args().skip(1).collect();
+    if paths.is_empty() || paths.iter().any(|p| p == "-h" || p == "--help") {
+        eprintln!("{}", USAGE);
+        ::std::process::exit(2);
+    }
+
+    let mut errors = 0;
+    for path in &paths {
+        for diagnostic in validate_path(path) {
+            if diagnostic.severity == Severity::Error {
+                errors += 1;
+            }
+            if diagnostic.line > 0 {
+                println!("{}:{}", path, diagnostic);
+            } else {
+                println!("{}: {}", path, diagnostic);
+            }
+        }
+    }
+
+    if errors > 0 {
+        eprintln!("{} error(s) found", errors);
+        ::std::process::exit(1);
+    }
+}
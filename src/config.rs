@@ -0,0 +1,152 @@
+// src/config.rs
+
+use level::campaign::START_LIVES;
+use log::LogLevelFilter;
+
+pub const USAGE: &'static str = "\
+Usage: platform-rs [options]
+
+Options:
+    -v, --verbose         increase the log verbosity, may be repeated
+    -q, --quiet           only log errors
+    --log SPEC            log filter, e.g. `info` or `warn,phi::gfx=debug`
+    --log-file PATH       also write the log to PATH, rotating it when full
+    --record PATH         record the input of every game to PATH
+    --replay PATH         play back the game recorded in PATH
+    --seed N              seed of the random generator of the levels
+    --lives N             lives of the player at the start of a game, 3 by default
+    --edit PATH           open the level at PATH in the editor
+    --debug               enable the pause (F5), step (F6) and time scale (F7, F8) keys
+    -h, --help            show this message";
+
+/// Options collected from the command line.
+#[derive(Debug)]
+pub struct Config {
+    pub log_level: LogLevelFilter,
+    pub log_targets: Vec<(String, LogLevelFilter)>,
+    pub log_file: Option<String>,
+    pub record: Option<String>,
+    pub replay: Option<String>,
+    pub seed: Option<u32>,
+    pub lives: u32,
+    pub edit: Option<String>,
+
+    /// enables the debug controls over the simulation time
+    pub debug: bool,
+}
+
+impl Config {
+    pub fn new() -> Config {
+        Config {
+            log_level: LogLevelFilter::Warn,
+            log_targets: Vec::new(),
+            log_file: None,
+            record: None,
+            replay: None,
+            seed: None,
+            lives: START_LIVES,
+            edit: None,
+            debug: false,
+        }
+    }
+
+    /// Parse the program arguments, `args` must not contain the program name.
+    ///
+    /// `Err` holds the message to show to the user, it's empty when the user
+    /// asked for help.
+    pub fn from_args<I>(args: I) -> Result<Config, String>
+        where I: Iterator<Item=String> {
+
+        let mut config = Config::new();
+        let mut args = args;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-h" | "--help" => {
+                    return Err(String::new());
+                },
+                "-v" | "--verbose" => {
+                    config.log_level = match config.log_level {
+                        LogLevelFilter::Off => LogLevelFilter::Error,
+                        LogLevelFilter::Error => LogLevelFilter::Warn,
+                        LogLevelFilter::Warn => LogLevelFilter::Info,
+                        LogLevelFilter::Info => LogLevelFilter::Debug,
+                        _ => LogLevelFilter::Trace,
+                    };
+                },
+                "-q" | "--quiet" => {
+                    config.log_level = LogLevelFilter::Error;
+                },
+                "--log" => {
+                    let spec = Config::value(&mut args, &arg)?;
+                    config.parse_log_spec(&spec)?;
+                },
+                "--log-file" => {
+                    config.log_file = Some(Config::value(&mut args, &arg)?);
+                },
+                "--record" => {
+                    config.record = Some(Config::value(&mut args, &arg)?);
+                },
+                "--replay" => {
+                    config.replay = Some(Config::value(&mut args, &arg)?);
+                },
+                "--seed" => {
+                    let seed = Config::value(&mut args, &arg)?;
+                    config.seed = Some(seed.parse::<u32>()
+                                       .map_err(|_| format!("Invalid seed '{}'", seed))?);
+                },
+                "--lives" => {
+                    let lives = Config::value(&mut args, &arg)?;
+                    config.lives = match lives.parse::<u32>() {
+                        Ok(n) if n > 0 => n,
+                        _ => return Err(format!("Invalid number of lives '{}'", lives)),
+                    };
+                },
+                "--debug" => {
+                    config.debug = true;
+                },
+                "--edit" => {
+                    config.edit = Some(Config::value(&mut args, &arg)?);
+                },
+                _ => {
+                    return Err(format!("Unknown option '{}'", arg));
+                }
+            }
+        }
+
+        if config.record.is_some() && config.replay.is_some() {
+            return Err("Options '--record' and '--replay' can't be used together".to_string());
+        }
+
+        if config.edit.is_some() && config.replay.is_some() {
+            return Err("Options '--edit' and '--replay' can't be used together".to_string());
+        }
+
+        Ok(config)
+    }
+
+    fn value<I>(args: &mut I, option: &str) -> Result<String, String>
+        where I: Iterator<Item=String> {
+        args.next().ok_or_else(|| format!("Option '{}' requires a value", option))
+    }
+
+    // SPEC is a comma separated list of `level` or `target=level` items
+    fn parse_log_spec(&mut self, spec: &str) -> Result<(), String> {
+        for item in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let mut parts = item.splitn(2, '=');
+            let first = parts.next().unwrap();
+            match parts.next() {
+                Some(level) => {
+                    let level = level.parse::<LogLevelFilter>()
+                        .map_err(|_| format!("Invalid log level '{}'", level))?;
+                    self.log_targets.push((first.to_string(), level));
+                },
+                None => {
+                    self.log_level = first.parse::<LogLevelFilter>()
+                        .map_err(|_| format!("Invalid log level '{}'", first))?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
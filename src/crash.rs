@@ -0,0 +1,122 @@
+// src/crash.rs
+
+use config::Config;
+use phi::KeyState;
+
+use std::backtrace::Backtrace;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::prelude::*;
+use std::panic;
+use std::sync::{Mutex, MutexGuard, TryLockError};
+
+/// Number of input frames kept for the report, a few seconds of play.
+const MAX_FRAMES: usize = 180;
+
+/// What the game was doing, kept up to date by the views so that the panic
+/// hook can describe it.
+struct CrashContext {
+    config: String,
+    view: String,
+    level: Option<String>,
+    player: Option<String>,
+    frames: VecDeque<String>,
+}
+
+static CONTEXT: Mutex<Option<CrashContext>> = Mutex::new(None);
+
+// the hook may run while the lock is held, a poisoned lock is still usable
+fn context() -> MutexGuard<'static, Option<CrashContext>> {
+    CONTEXT.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Install a panic hook writing a `crash-<date>.txt` report in the current
+/// directory, on top of the usual message on stderr.
+pub fn install(config: &Config) {
+    *context() = Some(CrashContext {
+        config: format!("{:#?}", config),
+        view: "none".to_string(),
+        level: None,
+        player: None,
+        frames: VecDeque::with_capacity(MAX_FRAMES),
+    });
+
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+
+        let path = format!("crash-{}.txt",
+                           ::time::now().strftime("%Y%m%d-%H%M%S").unwrap());
+        match write_report(&path, &info.to_string()) {
+            Ok(()) => error!("crash report written to {}", path),
+            Err(e) => error!("cannot write the crash report {}: {}", path, e),
+        }
+    }));
+}
+
+fn write_report(path: &str, message: &str) -> ::std::io::Result<()> {
+    let mut file = File::create(path)?;
+
+    writeln!(file, "platform-rs {} crash report", env!("CARGO_PKG_VERSION"))?;
+    writeln!(file, "{}\n", message)?;
+    writeln!(file, "Backtrace:\n{}\n", Backtrace::force_capture())?;
+
+    // a panic while holding the lock would deadlock here, don't wait for it
+    let guard = match CONTEXT.try_lock() {
+        Ok(guard) => Some(guard),
+        Err(TryLockError::Poisoned(e)) => Some(e.into_inner()),
+        Err(TryLockError::WouldBlock) => None,
+    };
+
+    if let Some(Some(ref context)) = guard.as_ref().map(|g| &**g) {
+        writeln!(file, "View: {}", context.view)?;
+        writeln!(file, "Level: {}", context.level.as_ref().map_or("none", |s| s))?;
+        writeln!(file, "Player: {}\n", context.player.as_ref().map_or("none", |s| s))?;
+
+        writeln!(file, "Config:\n{}\n", context.config)?;
+
+        writeln!(file, "Last {} input frames, oldest first:", context.frames.len())?;
+        for frame in &context.frames {
+            writeln!(file, "{}", frame)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Record the name of the view being run.
+pub fn set_view(name: &str) {
+    if let Some(ref mut context) = *context() {
+        context.view = name.to_string();
+    }
+}
+
+/// Record the path of the level being played, `None` outside of the game.
+pub fn set_level(path: Option<&str>) {
+    if let Some(ref mut context) = *context() {
+        context.level = path.map(str::to_string);
+    }
+}
+
+/// Record the position and the velocity of the player.
+pub fn set_player(x: f64, y: f64, vx: f32, vy: f32) {
+    if let Some(ref mut context) = *context() {
+        context.player = Some(format!("pos ({}, {}) vel ({}, {})", x, y, vx, vy));
+    }
+}
+
+/// Record the input of a frame, in the format of the replay files.
+pub fn push_frame(elapsed: f64, keys: &KeyState) {
+    if let Some(ref mut context) = *context() {
+        if context.frames.len() == MAX_FRAMES {
+            context.frames.pop_front();
+        }
+
+        let mut line = format!("frame {:?}", elapsed);
+        for name in keys.names() {
+            line.push(' ');
+            line.push_str(name);
+        }
+        context.frames.push_back(line);
+    }
+}
@@ -0,0 +1,165 @@
+// src/level/campaign.rs
+
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::Path;
+
+/// Ordered list of levels read by `Campaign::load` when it exists.
+pub const MANIFEST: &'static str = "assets/campaign.txt";
+
+// the levels found by `Campaign::discover` are the prefix followed by the
+// number and one of the extensions
+const LEVEL_PREFIX: &'static str = "assets/level-";
+const LEVEL_EXTENSIONS: [&'static str; 3] = [".txt", ".tmx", ".json"];
+
+/// Lives of the player at the start of a campaign, unless given with
+/// `--lives`.
+pub const START_LIVES: u32 = 3;
+
+/// The player earns an extra life every time the score goes past a multiple
+/// of this.
+pub const EXTRA_LIFE_POINTS: u32 = 1000;
+
+/// The levels played one after the other in a game.
+#[derive(Clone, Debug)]
+pub struct Campaign {
+    pub levels: Vec<String>,
+}
+
+impl Campaign {
+    /// Read the manifest if there is one, otherwise discover the levels.
+    pub fn load() -> Result<Campaign, String> {
+        if Path::new(MANIFEST).exists() {
+            Campaign::from_manifest(MANIFEST)
+        } else {
+            Campaign::discover()
+        }
+    }
+
+    /// Read the paths of the levels from `path`, one per line, blank lines
+    /// and lines starting with `#` are ignored.
+    pub fn from_manifest(path: &str) -> Result<Campaign, String> {
+        let mut text = String::new();
+        File::open(path)
+            .and_then(|mut f| f.read_to_string(&mut text))
+            .map_err(|e| format!("{}: {}", path, e))?;
+
+        let levels: Vec<String> = text.lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .map(str::to_string)
+            .collect();
+
+        if levels.is_empty() {
+            return Err(format!("{}: the campaign has no levels", path));
+        }
+        Ok(Campaign {
+            levels: levels,
+        })
+    }
+
+    /// Find `assets/level-0.txt`, `assets/level-1.txt` and so on up to the
+    /// first missing number, the Tiled maps `level-N.tmx` and `level-N.json`
+    /// are found as well.
+    pub fn discover() -> Result<Campaign, String> {
+        let mut levels = Vec::new();
+
+        'numbers: for n in 0.. {
+            for extension in &LEVEL_EXTENSIONS {
+                let path = format!("{}{}{}", LEVEL_PREFIX, n, extension);
+                if Path::new(&path).exists() {
+                    levels.push(path);
+                    continue 'numbers;
+                }
+            }
+            break;
+        }
+
+        if levels.is_empty() {
+            return Err(format!("no levels found, expected {}0{}",
+                               LEVEL_PREFIX, LEVEL_EXTENSIONS[0]));
+        }
+        Ok(Campaign {
+            levels: levels,
+        })
+    }
+
+    /// A campaign made of a single level.
+    pub fn single(path: &str) -> Campaign {
+        Campaign {
+            levels: vec![path.to_string()],
+        }
+    }
+
+    /// Index of the level at `path`, the levels missing from the campaign are
+    /// added at the end.
+    pub fn index_of(&mut self, path: &str) -> usize {
+        match self.levels.iter().position(|l| l == path) {
+            Some(index) => index,
+            None => {
+                self.levels.push(path.to_string());
+                self.levels.len() - 1
+            }
+        }
+    }
+}
+
+/// What the player carries from a level to the next one.
+#[derive(Clone, Debug)]
+pub struct Session {
+    /// index of the current level in the campaign
+    pub level: usize,
+
+    pub score: u32,
+    pub lives: u32,
+
+    // extra lives already earned, so that a score going down and up again
+    // doesn't earn the same one twice
+    extra_lives: u32,
+
+    /// seed of the first level given on the command line, the following ones
+    /// use the next seeds; `None` lets every level pick its own seed
+    pub seed: Option<u32>,
+}
+
+impl Session {
+    pub fn new(seed: Option<u32>, lives: u32) -> Session {
+        Session {
+            level: 0,
+            score: 0,
+            lives: lives,
+            extra_lives: 0,
+            seed: seed,
+        }
+    }
+
+    /// Session going on with `score` and `lives`, the extra lives of the
+    /// score were already given.
+    pub fn resume(seed: Option<u32>, score: u32, lives: u32) -> Session {
+        Session {
+            score: score,
+            extra_lives: score / EXTRA_LIFE_POINTS,
+            ..Session::new(seed, lives)
+        }
+    }
+
+    /// Give the extra lives earned by reaching `score`, the score of the
+    /// session and the points of the level being played, and return how many
+    /// were given.
+    pub fn earn_lives(&mut self, score: u32) -> u32 {
+        let earned = score / EXTRA_LIFE_POINTS;
+        if earned <= self.extra_lives {
+            return 0
+        }
+
+        let new_lives = earned - self.extra_lives;
+        self.extra_lives = earned;
+        self.lives += new_lives;
+        new_lives
+    }
+
+    /// Seed of the current level.
+    pub fn level_seed(&self) -> Option<u32> {
+        self.seed.map(|seed| seed.wrapping_add(self.level as u32))
+    }
+}
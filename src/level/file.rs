@@ -0,0 +1,707 @@
+// src/level/file.rs
+
+use level::LevelError;
+
+use std::fs::File;
+use std::io;
+use std::io::BufWriter;
+use std::io::prelude::*;
+
+pub const DEFAULT_TILE_WIDTH: f64 = 40.0;
+pub const DEFAULT_TILE_HEIGHT: f64 = 32.0;
+
+/// Seconds to complete the levels without a `time` property.
+pub const DEFAULT_TIME_LIMIT: f64 = 120.0;
+
+/// Speed of the moving platforms without a `speed` property, in pixels per
+/// second.
+pub const DEFAULT_PLATFORM_SPEED: f64 = 64.0;
+
+pub const DEFAULT_BACKGROUNDS: [&'static str; 3] = [
+    "assets/background0.png",
+    "assets/background1.png",
+    "assets/background2.png",
+];
+
+/// A `key = value` entry of the `[level]` or of a `[platform]` section.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Property {
+    pub key: String,
+    pub value: String,
+    pub line: usize,
+
+    /// comments and blank lines right before the entry, written back as
+    /// they were read
+    pub comments: Vec<String>,
+}
+
+/// A level as written on disk.
+///
+/// A level file is either a bare grid of tile characters or a list of
+/// sections, a line with the section name between square brackets followed
+/// by its content:
+///
+/// ```text
+/// [level]
+/// name = The beginning
+/// time = 120
+///
+/// [tiles]
+/// .1................X.
+/// ####################
+/// ```
+///
+/// The optional `[level]` section holds the metadata as `key = value` lines,
+/// blank lines and lines starting with `#` are comments. The `[tiles]`
+/// section holds the grid, one line per row of tiles.
+///
+/// The optional `[variants]` section is a grid as large as the tiles which
+/// records the variant drawn by the tiles with more than one sprite, `0` to
+/// `9` then `a` to `z`, and `.` for the tiles left to the random generator.
+///
+/// The `[background]` and `[foreground]` sections are decoration grids drawn
+/// behind the tiles and over the player, see `DecorationLayer`. There may be
+/// several of each, told apart by a name after the section name such as
+/// `[foreground grass]`, they are drawn in the order of the file. The
+/// variants of a layer are recorded like the ones of the tiles, in a section
+/// named after it such as `[variants foreground grass]` which follows it.
+///
+/// Each `[platform]` section, also told apart by a name, holds the `key =
+/// value` lines of a moving platform, see `PlatformDef`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LevelFile {
+    pub properties: Vec<Property>,
+    pub tiles: Vec<String>,
+
+    /// line of the first row of `tiles`, starting from 1
+    pub tiles_line: usize,
+
+    pub variants: Vec<String>,
+
+    /// line of the first row of `variants`, 0 without the section
+    pub variants_line: usize,
+
+    pub layers: Vec<DecorationLayer>,
+    pub platforms: Vec<PlatformSection>,
+
+    /// comments and blank lines after the last property of the `[level]`
+    /// section, but for the blank line separating it from the next section
+    pub comments: Vec<String>,
+
+    /// the lines end with `\r\n`, they are written back the same way
+    pub crlf: bool,
+}
+
+/// A grid of tiles drawn for decoration only: the collision and the flags of
+/// its tiles are ignored.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecorationLayer {
+    /// the name following the section name, may be empty
+    pub name: String,
+
+    /// drawn over the player instead of behind the tiles
+    pub foreground: bool,
+    pub rows: Vec<String>,
+
+    /// line of the first row, starting from 1
+    pub line: usize,
+
+    /// the variants of the tiles of `rows`, like `LevelFile::variants`
+    pub variants: Vec<String>,
+
+    /// line of the first row of `variants`, 0 without the section
+    pub variants_line: usize,
+}
+
+/// The `key = value` lines of a `[platform]` section.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlatformSection {
+    /// the name following the section name, may be empty
+    pub name: String,
+    pub properties: Vec<Property>,
+
+    /// line of the section name, starting from 1
+    pub line: usize,
+
+    /// comments and blank lines after the last property, see
+    /// `LevelFile::comments`
+    pub comments: Vec<String>,
+}
+
+/// A platform moving back and forth along a path, or around it when the
+/// path is a loop:
+///
+/// ```text
+/// [platform lift]
+/// tile = -
+/// width = 3
+/// path = 4,10 4,4
+/// speed = 48
+/// ```
+///
+/// The waypoints of `path` are the tiles, `x,y` from the top left corner of
+/// the level, where the left end of the platform goes. A horizontal or a
+/// vertical platform has two waypoints in the same row or column.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlatformDef {
+    /// tile of the tileset drawn for every tile of the platform, whose
+    /// collision is the one of the platform, `-` by default
+    pub tile: char,
+
+    /// width in tiles, 1 by default
+    pub width: usize,
+    pub path: Vec<(usize, usize)>,
+
+    /// `loop = true` goes from the last waypoint straight to the first one
+    /// instead of back along the path
+    pub looped: bool,
+
+    /// pixels per second
+    pub speed: f64,
+}
+
+impl LevelFile {
+    pub fn load(path: &str) -> Result<LevelFile, LevelError> {
+        let mut text = String::new();
+        File::open(path)
+            .and_then(|mut f| f.read_to_string(&mut text))
+            .map_err(|e| LevelError::new(0, &e.to_string()))?;
+
+        LevelFile::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<LevelFile, LevelError> {
+        let lines: Vec<&str> = text.lines()
+            .map(|l| l.trim_end_matches('\r'))
+            .collect();
+        let crlf = text.contains("\r\n");
+
+        // a bare grid, the format used before the sections were introduced
+        if !lines.first().map_or(false, |l| l.starts_with('[')) {
+            return Ok(LevelFile {
+                properties: Vec::new(),
+                tiles: lines.iter().map(|l| l.to_string()).collect(),
+                tiles_line: 1,
+                variants: Vec::new(),
+                variants_line: 0,
+                layers: Vec::new(),
+                platforms: Vec::new(),
+                comments: Vec::new(),
+                crlf: crlf,
+            });
+        }
+
+        let mut file = LevelFile {
+            properties: Vec::new(),
+            tiles: Vec::new(),
+            tiles_line: 0,
+            variants: Vec::new(),
+            variants_line: 0,
+            layers: Vec::new(),
+            platforms: Vec::new(),
+            comments: Vec::new(),
+            crlf: crlf,
+        };
+
+        let mut section = "";
+        let mut seen: Vec<String> = Vec::new();
+
+        // comments and blank lines waiting for the next property
+        let mut comments: Vec<String> = Vec::new();
+        for (i, line) in lines.iter().enumerate() {
+            let lineno = i + 1;
+
+            if line.starts_with('[') {
+                if !line.ends_with(']') {
+                    return Err(LevelError::new(lineno, "unterminated section name"));
+                }
+
+                // the last blank line is the one `write` puts between sections
+                if comments.last().map_or(false, |l| l.trim().is_empty()) {
+                    comments.pop();
+                }
+                file.end_section(section, comments);
+                comments = Vec::new();
+
+                section = &line[1..line.len() - 1];
+                if seen.iter().any(|s| s == section) {
+                    return Err(LevelError::new(
+                        lineno, &format!("duplicate section [{}]", section)));
+                }
+                seen.push(section.to_string());
+
+                match section {
+                    "level" => {},
+                    "tiles" => file.tiles_line = lineno + 1,
+                    "variants" => file.variants_line = lineno + 1,
+                    _ => if let Some(name) = platform_section(section) {
+                        file.platforms.push(PlatformSection {
+                            name: name.to_string(),
+                            properties: Vec::new(),
+                            line: lineno,
+                            comments: Vec::new(),
+                        });
+                    } else if let Some(layer) = layer_variants_section(section) {
+                        match file.layer_mut(layer) {
+                            Some(layer) => layer.variants_line = lineno + 1,
+                            None => return Err(LevelError::new(
+                                lineno, &format!("[{}] doesn't follow its layer", section))),
+                        }
+                    } else {
+                        match layer_section(section) {
+                            Some((foreground, name)) => file.layers.push(DecorationLayer {
+                                name: name.to_string(),
+                                foreground: foreground,
+                                rows: Vec::new(),
+                                line: lineno + 1,
+                                variants: Vec::new(),
+                                variants_line: 0,
+                            }),
+                            None => return Err(LevelError::new(
+                                lineno, &format!("unknown section [{}]", section))),
+                        }
+                    },
+                }
+                continue;
+            }
+
+            match section {
+                "level" => match parse_property(line, lineno)? {
+                    Some(property) => file.properties.push(property.after(&mut comments)),
+                    None => comments.push(line.to_string()),
+                },
+                _ if platform_section(section).is_some() => match parse_property(line, lineno)? {
+                    Some(property) => {
+                        let platform = file.platforms.last_mut().unwrap();
+                        platform.properties.push(property.after(&mut comments));
+                    },
+                    None => comments.push(line.to_string()),
+                },
+                "variants" => {
+                    file.variants.push(line.to_string());
+                },
+                _ if layer_variants_section(section).is_some() => {
+                    let layer = file.layer_mut(layer_variants_section(section).unwrap()).unwrap();
+                    layer.variants.push(line.to_string());
+                },
+                _ if layer_section(section).is_some() => {
+                    file.layers.last_mut().unwrap().rows.push(line.to_string());
+                },
+                _ => {
+                    file.tiles.push(line.to_string());
+                }
+            }
+        }
+
+        file.end_section(section, comments);
+
+        if file.tiles_line == 0 {
+            return Err(LevelError::new(lines.len(), "missing [tiles] section"));
+        }
+
+        // blank lines separating the grids from the next section
+        let grids = file.layers.iter_mut()
+            .flat_map(|layer| vec![&mut layer.rows, &mut layer.variants])
+            .chain(vec![&mut file.tiles, &mut file.variants]);
+        for grid in grids {
+            while grid.last().map_or(false, |l| l.trim().is_empty()) {
+                grid.pop();
+            }
+        }
+
+        Ok(file)
+    }
+
+    /// Write the level to `path`, see `write`.
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+        self.write(&mut file)?;
+        file.flush()
+    }
+
+    /// Write the level in the format read by `parse`.
+    ///
+    /// Writing a parsed file gives back the same text, comments and blank
+    /// lines included, as long as its sections are in the order of this
+    /// function, separated by a blank line, and its properties are written
+    /// `key = value`. A file with neither properties, variants, layers nor
+    /// platforms is written as a bare grid.
+    pub fn write<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        if !self.crlf {
+            return self.write_lines(out);
+        }
+
+        let mut text = Vec::new();
+        self.write_lines(&mut text)?;
+        out.write_all(String::from_utf8_lossy(&text).replace('\n', "\r\n").as_bytes())
+    }
+
+    // write the lines of the level ended by `\n`
+    fn write_lines<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        if self.properties.is_empty() && self.comments.is_empty() && self.variants.is_empty() &&
+            self.layers.is_empty() && self.platforms.is_empty() {
+            for row in &self.tiles {
+                writeln!(out, "{}", row)?;
+            }
+            return Ok(());
+        }
+
+        if !self.properties.is_empty() || !self.comments.is_empty() {
+            writeln!(out, "[level]")?;
+            write_properties(out, &self.properties, &self.comments)?;
+            writeln!(out)?;
+        }
+
+        writeln!(out, "[tiles]")?;
+        for row in &self.tiles {
+            writeln!(out, "{}", row)?;
+        }
+
+        if !self.variants.is_empty() {
+            writeln!(out)?;
+            writeln!(out, "[variants]")?;
+            for row in &self.variants {
+                writeln!(out, "{}", row)?;
+            }
+        }
+
+        for layer in &self.layers {
+            writeln!(out)?;
+            let section = if layer.foreground { "foreground" } else { "background" };
+            if layer.name.is_empty() {
+                writeln!(out, "[{}]", section)?;
+            } else {
+                writeln!(out, "[{} {}]", section, layer.name)?;
+            }
+            for row in &layer.rows {
+                writeln!(out, "{}", row)?;
+            }
+
+            if !layer.variants.is_empty() {
+                writeln!(out)?;
+                if layer.name.is_empty() {
+                    writeln!(out, "[variants {}]", section)?;
+                } else {
+                    writeln!(out, "[variants {} {}]", section, layer.name)?;
+                }
+                for row in &layer.variants {
+                    writeln!(out, "{}", row)?;
+                }
+            }
+        }
+
+        for platform in &self.platforms {
+            writeln!(out)?;
+            if platform.name.is_empty() {
+                writeln!(out, "[platform]")?;
+            } else {
+                writeln!(out, "[platform {}]", platform.name)?;
+            }
+            write_properties(out, &platform.properties, &platform.comments)?;
+        }
+
+        Ok(())
+    }
+
+    // the layer of the `[background]` or `[foreground]` section named `name`
+    fn layer_mut(&mut self, (foreground, name): (bool, &str)) -> Option<&mut DecorationLayer> {
+        self.layers.iter_mut().find(|layer| layer.foreground == foreground && layer.name == name)
+    }
+
+    // give the comments and blank lines left at the end of `section` to it,
+    // only the sections of properties have some
+    fn end_section(&mut self, section: &str, comments: Vec<String>) {
+        if section == "level" {
+            self.comments = comments;
+        } else if platform_section(section).is_some() {
+            self.platforms.last_mut().unwrap().comments = comments;
+        }
+    }
+
+    /// Variant recorded for the tile at `x`, `y`, `None` when the tile is
+    /// left to the random generator. Invalid characters are `Some(Err)`.
+    pub fn variant(&self, x: usize, y: usize) -> Option<Result<usize, char>> {
+        variant_at(&self.variants, x, y)
+    }
+
+    /// Typed view of the `[level]` section.
+    pub fn meta(&self) -> Result<LevelMeta, LevelError> {
+        LevelMeta::from_properties(&self.properties)
+    }
+
+    /// Typed view of the `[platform]` sections.
+    pub fn platforms(&self) -> Result<Vec<PlatformDef>, LevelError> {
+        self.platforms.iter()
+            .map(|section| PlatformDef::from_properties(&section.properties, section.line))
+            .collect()
+    }
+}
+
+// a `key = value` line of a section, `None` for the blank lines and the
+// comments
+fn parse_property(line: &str, lineno: usize) -> Result<Option<Property>, LevelError> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+
+    let mut parts = line.splitn(2, '=');
+    let key = parts.next().unwrap().trim();
+    match parts.next() {
+        Some(value) => Ok(Some(Property::new(key, value.trim(), lineno))),
+        None => Err(LevelError::new(
+            lineno, &format!("expected `key = value`, found '{}'", line))),
+    }
+}
+
+// the properties of a section with their comments, `comments` goes after
+// the last one
+fn write_properties<W: Write>(out: &mut W, properties: &[Property],
+                              comments: &[String]) -> io::Result<()> {
+    for p in properties {
+        for line in &p.comments {
+            writeln!(out, "{}", line)?;
+        }
+        writeln!(out, "{} = {}", p.key, p.value)?;
+    }
+    for line in comments {
+        writeln!(out, "{}", line)?;
+    }
+    Ok(())
+}
+
+// the name of a platform section, `None` when `section` is not one
+fn platform_section(section: &str) -> Option<&str> {
+    let mut parts = section.splitn(2, ' ');
+    match parts.next() {
+        Some("platform") => Some(parts.next().unwrap_or("").trim()),
+        _ => None,
+    }
+}
+
+// whether `section` is a foreground layer rather than a background one, and
+// its name, `None` when it is not a layer
+fn layer_section(section: &str) -> Option<(bool, &str)> {
+    let mut parts = section.splitn(2, ' ');
+    let foreground = match parts.next() {
+        Some("background") => false,
+        Some("foreground") => true,
+        _ => return None,
+    };
+    Some((foreground, parts.next().unwrap_or("").trim()))
+}
+
+// the layer whose variants are in `section`, `None` when it is not a
+// variants section of a layer
+fn layer_variants_section(section: &str) -> Option<(bool, &str)> {
+    if section.starts_with("variants ") {
+        layer_section(&section["variants ".len()..])
+    } else {
+        None
+    }
+}
+
+/// Variant recorded in the grid `variants` for the tile at `x`, `y`, see
+/// `LevelFile::variant`.
+pub fn variant_at(variants: &[String], x: usize, y: usize) -> Option<Result<usize, char>> {
+    match variants.get(y).and_then(|row| row.chars().nth(x)) {
+        None | Some('.') => None,
+        Some(c) => Some(c.to_digit(36)
+                        .filter(|_| !c.is_uppercase())
+                        .map(|d| d as usize)
+                        .ok_or(c)),
+    }
+}
+
+impl DecorationLayer {
+    /// Variant recorded for the tile at `x`, `y`, see `LevelFile::variant`.
+    pub fn variant(&self, x: usize, y: usize) -> Option<Result<usize, char>> {
+        variant_at(&self.variants, x, y)
+    }
+}
+
+/// Character recording the variant `index` in the `[variants]` section.
+pub fn variant_char(index: usize) -> char {
+    ::std::char::from_digit(index as u32, 36).expect("at most 36 variants per tile")
+}
+
+impl Property {
+    pub fn new(key: &str, value: &str, line: usize) -> Property {
+        Property {
+            key: key.to_string(),
+            value: value.to_string(),
+            line: line,
+            comments: Vec::new(),
+        }
+    }
+
+    // the property with the comments read before it
+    fn after(mut self, comments: &mut Vec<String>) -> Property {
+        self.comments = ::std::mem::replace(comments, Vec::new());
+        self
+    }
+
+    fn parse<T: ::std::str::FromStr>(&self) -> Result<T, LevelError> {
+        self.value.parse::<T>().map_err(|_| LevelError::new(
+            self.line, &format!("invalid value '{}' for '{}'", self.value, self.key)))
+    }
+}
+
+/// Metadata of a level, the defaults are used for the missing properties.
+#[derive(Clone, Debug)]
+pub struct LevelMeta {
+    pub name: Option<String>,
+
+    /// time to complete the level in seconds
+    pub time_limit: f64,
+
+    /// images drawn behind the tiles, the first one at the bottom
+    pub backgrounds: Vec<String>,
+
+    pub music: Option<String>,
+    pub tileset: Option<String>,
+    pub tile_width: f64,
+    pub tile_height: f64,
+
+    /// downward acceleration of the player, in pixels per second squared
+    pub gravity: Option<f32>,
+
+    /// path of the level following this one
+    pub next: Option<String>,
+
+    /// seed of the random generator, the command line takes precedence
+    pub seed: Option<u32>,
+}
+
+impl Default for LevelMeta {
+    fn default() -> LevelMeta {
+        LevelMeta {
+            name: None,
+            time_limit: DEFAULT_TIME_LIMIT,
+            backgrounds: DEFAULT_BACKGROUNDS.iter().map(|s| s.to_string()).collect(),
+            music: None,
+            tileset: None,
+            tile_width: DEFAULT_TILE_WIDTH,
+            tile_height: DEFAULT_TILE_HEIGHT,
+            gravity: None,
+            next: None,
+            seed: None,
+        }
+    }
+}
+
+impl PlatformDef {
+    /// Build the platform from the properties of its section, which starts at
+    /// `line`. The path is the only required property and needs at least two
+    /// waypoints.
+    pub fn from_properties(properties: &[Property], line: usize) -> Result<PlatformDef, LevelError> {
+        let mut platform = PlatformDef {
+            tile: '-',
+            width: 1,
+            path: Vec::new(),
+            looped: false,
+            speed: DEFAULT_PLATFORM_SPEED,
+        };
+
+        for p in properties {
+            match p.key.as_str() {
+                "tile" => {
+                    let mut chars = p.value.chars();
+                    platform.tile = match (chars.next(), chars.next()) {
+                        (Some(c), None) => c,
+                        _ => return Err(LevelError::new(
+                            p.line, &format!("expected one tile character, not '{}'", p.value))),
+                    };
+                },
+                "width" => {
+                    platform.width = p.parse::<usize>()?;
+                    if platform.width == 0 {
+                        return Err(LevelError::new(p.line, "the width must be at least 1"));
+                    }
+                },
+                "path" => {
+                    platform.path = p.value.split_whitespace().map(|waypoint| {
+                        let mut coords = waypoint.splitn(2, ',').map(|c| c.trim().parse::<usize>());
+                        match (coords.next(), coords.next()) {
+                            (Some(Ok(x)), Some(Ok(y))) => Ok((x, y)),
+                            _ => Err(LevelError::new(
+                                p.line, &format!("invalid waypoint '{}', expected x,y", waypoint))),
+                        }
+                    }).collect::<Result<Vec<_>, _>>()?;
+
+                    // the platform can't move between two waypoints at the same place
+                    if let Some(w) = platform.path.windows(2).find(|w| w[0] == w[1]) {
+                        return Err(LevelError::new(
+                            p.line, &format!("waypoint {},{} repeats the previous one",
+                                             w[1].0, w[1].1)));
+                    }
+                },
+                "loop" => platform.looped = p.parse::<bool>()?,
+                "speed" => {
+                    platform.speed = p.parse::<f64>()?;
+                    if !(platform.speed > 0.0) {
+                        return Err(LevelError::new(
+                            p.line, &format!("the speed must be positive, not {}", p.value)));
+                    }
+                },
+                _ => return Err(LevelError::new(
+                    p.line, &format!("unknown property '{}'", p.key))),
+            }
+        }
+
+        if platform.path.len() < 2 {
+            return Err(LevelError::new(line, "the platform path needs at least two waypoints"));
+        }
+        Ok(platform)
+    }
+}
+
+impl LevelMeta {
+    /// Build the metadata from the properties of a level, the later values
+    /// override the earlier ones, except for `background` which may be
+    /// repeated to add more layers.
+    pub fn from_properties(properties: &[Property]) -> Result<LevelMeta, LevelError> {
+        let mut meta = LevelMeta::default();
+        let mut backgrounds = Vec::new();
+
+        for p in properties {
+            match p.key.as_str() {
+                "name" => meta.name = Some(p.value.clone()),
+                "time" => {
+                    meta.time_limit = p.parse::<f64>()?;
+                    if !(meta.time_limit > 0.0) {
+                        return Err(LevelError::new(
+                            p.line, &format!("the time must be positive, not {}", p.value)));
+                    }
+                },
+                "background" => backgrounds.push(p.value.clone()),
+                "music" => meta.music = Some(p.value.clone()),
+                "tileset" => meta.tileset = Some(p.value.clone()),
+                "tile_width" | "tile_height" => {
+                    let size = p.parse::<f64>()?;
+                    if !(size > 0.0) {
+                        return Err(LevelError::new(
+                            p.line, &format!("the {} must be positive, not {}", p.key, p.value)));
+                    }
+                    if p.key == "tile_width" {
+                        meta.tile_width = size;
+                    } else {
+                        meta.tile_height = size;
+                    }
+                },
+                "gravity" => meta.gravity = Some(p.parse::<f32>()?),
+                "next" => meta.next = Some(p.value.clone()),
+                "seed" => meta.seed = Some(p.parse::<u32>()?),
+                _ => return Err(LevelError::new(
+                    p.line, &format!("unknown property '{}'", p.key))),
+            }
+        }
+
+        // `background = none` removes the default layers
+        if !backgrounds.is_empty() {
+            meta.backgrounds = backgrounds.into_iter().filter(|b| b != "none").collect();
+        }
+
+        Ok(meta)
+    }
+}
@@ -0,0 +1,277 @@
+// src/level/generate.rs
+
+use level::file::{LevelFile, Property, DEFAULT_TILE_HEIGHT, DEFAULT_TILE_WIDTH};
+use level::physics::{jump_arc, PLAYER_GRAVITY_ACCEL};
+use level::tileset::{TileDef, Tileset};
+use phi::gfx::TileCollision;
+
+use rand::{Rng, SeedableRng, StdRng};
+
+// the jumps are checked with the steps of the game loop
+const FRAME_TIME: f32 = 1.0 / 60.0;
+
+// the feet must clear the top of the landing tile by this fraction of a tile
+const CLEARANCE: f64 = 0.5;
+
+// the reach of the jumps is cut by this factor to forgive a late take off
+const REACH_MARGIN: f64 = 0.8;
+
+// the deepest drop from a platform to the next one, in tiles
+const MAX_DROP: i32 = 4;
+
+// rows left free above the highest platforms
+const HEADROOM: usize = 3;
+
+/// Parameters of the generated levels.
+#[derive(Clone, Debug)]
+pub struct Params {
+    pub width: usize,
+    pub height: usize,
+
+    /// from 0, long platforms and short jumps, to 1, short platforms and
+    /// jumps at the limit of the player
+    pub difficulty: f64,
+    pub gems: usize,
+
+    pub tile_width: f64,
+    pub tile_height: f64,
+    pub gravity: f32,
+}
+
+impl Default for Params {
+    fn default() -> Params {
+        Params {
+            width: 60,
+            height: 15,
+            difficulty: 0.5,
+            gems: 10,
+            tile_width: DEFAULT_TILE_WIDTH,
+            tile_height: DEFAULT_TILE_HEIGHT,
+            gravity: PLAYER_GRAVITY_ACCEL,
+        }
+    }
+}
+
+/// How far the player can jump, in tiles.
+pub struct JumpReach {
+    arc: Vec<(f64, f64)>,
+    tile_width: f64,
+    tile_height: f64,
+}
+
+impl JumpReach {
+    pub fn new(params: &Params) -> JumpReach {
+        let depth = (MAX_DROP as f64 + 1.0) * params.tile_height;
+        JumpReach {
+            arc: jump_arc(params.gravity, FRAME_TIME, depth),
+            tile_width: params.tile_width,
+            tile_height: params.tile_height,
+        }
+    }
+
+    /// Widest gap, in tiles, the player can jump over to land `rise` tiles
+    /// higher, or lower when negative. `None` when the height can't be
+    /// reached at all.
+    ///
+    /// The player takes off from the center of the last tile before the gap
+    /// and must be above the center of the first tile after it while the feet
+    /// are still above the landing tile.
+    pub fn max_gap(&self, rise: i32) -> Option<usize> {
+        let height = (rise as f64 + CLEARANCE) * self.tile_height;
+        let reach = self.arc.iter()
+            .filter(|&&(_, y)| y >= height)
+            .map(|&(x, _)| x)
+            .fold(None, |max: Option<f64>, x| Some(max.map_or(x, |m| m.max(x))));
+
+        reach.and_then(|reach| {
+            let tiles = (reach * REACH_MARGIN / self.tile_width).floor() as i64 - 1;
+            if tiles >= 0 { Some(tiles as usize) } else { None }
+        })
+    }
+
+    /// Highest rise, in tiles, the player can jump onto.
+    pub fn max_rise(&self) -> i32 {
+        let mut rise = 0;
+        while self.max_gap(rise + 1).is_some() {
+            rise += 1;
+        }
+        rise
+    }
+}
+
+// the characters of the tileset used by the generator
+struct Legend {
+    blank: char,
+    ground: char,
+    platforms: Vec<char>,
+    start: char,
+    exit: char,
+    gem: char,
+}
+
+impl Legend {
+    fn new(tileset: &Tileset) -> Result<Legend, String> {
+        let platforms: Vec<char> = tileset.tiles.iter()
+            .filter(|t| t.collision == TileCollision::Platform && t.flags == Default::default())
+            .map(|t| t.symbol)
+            .collect();
+        if platforms.is_empty() {
+            return Err("the tileset has no platform tile".to_string());
+        }
+
+        Ok(Legend {
+            blank: find(tileset, "blank", |t| t.collision == TileCollision::Passable &&
+                        t.sprites.is_empty() && t.flags == Default::default())?,
+            ground: find(tileset, "impassable", |t| t.collision == TileCollision::Impassable &&
+                         t.flags == Default::default())?,
+            platforms: platforms,
+            start: find(tileset, "start",
+                        |t| t.flags.start && t.collision == TileCollision::Passable)?,
+            exit: find(tileset, "exit",
+                       |t| t.flags.exit && t.collision == TileCollision::Passable)?,
+            gem: find(tileset, "gem",
+                      |t| t.flags.gem && t.collision == TileCollision::Passable)?,
+        })
+    }
+}
+
+// the first tile of the tileset matching `f`
+fn find<F>(tileset: &Tileset, what: &str, f: F) -> Result<char, String>
+    where F: Fn(&TileDef) -> bool {
+    tileset.tiles.iter().find(|t| f(t)).map(|t| t.symbol)
+        .ok_or_else(|| format!("the tileset has no {} tile", what))
+}
+
+// a run of tiles the player can stand on
+struct Segment {
+    x: usize,
+    len: usize,
+
+    // row of the tiles, the player stands in the row above
+    floor: usize,
+}
+
+/// Generate a level with the tiles of `tileset`, the same seed and
+/// parameters always give the same level.
+///
+/// The level is a chain of platforms from the start on the left to the exit
+/// on the right, every jump of the chain is checked against the jump of the
+/// player to make sure that the exit can be reached. The elevated platforms
+/// can be passed through from below, so that the player never hits a wall
+/// or a ceiling on the way.
+pub fn generate(seed: u32, params: &Params, tileset: &Tileset) -> Result<LevelFile, String> {
+    let legend = Legend::new(tileset)?;
+    if params.width < 8 {
+        return Err(format!("the level must be at least 8 tiles wide, not {}", params.width));
+    }
+    if params.height < HEADROOM + 3 {
+        return Err(format!("the level must be at least {} tiles high, not {}",
+                           HEADROOM + 3, params.height));
+    }
+
+    let reach = JumpReach::new(params);
+    let max_rise = reach.max_rise();
+    if reach.max_gap(0).is_none() {
+        return Err("the player can't jump with these parameters".to_string());
+    }
+
+    let mut rng = StdRng::from_seed(&[seed as usize]);
+    let difficulty = params.difficulty.max(0.0).min(1.0);
+    let ground = params.height - 1;
+    let mut grid = vec![vec![legend.blank; params.width]; params.height];
+
+    // flat ground to start with, then jumps up to the end of the level
+    let mut segments = vec![Segment { x: 0, len: 4, floor: ground }];
+    loop {
+        let (end, floor) = {
+            let last = segments.last().unwrap();
+            (last.x + last.len, last.floor)
+        };
+
+        // a harder level climbs and drops more
+        let span = 1 + (difficulty * max_rise.max(MAX_DROP) as f64).round() as i32;
+        let rise = rng.gen_range(-span.min(MAX_DROP), span.min(max_rise) + 1);
+        let target = (floor as i32 - rise).max(HEADROOM as i32 + 1).min(ground as i32) as usize;
+        let rise = floor as i32 - target as i32;
+
+        // the widest gap is always possible: a lower rise was chosen above
+        let max_gap = reach.max_gap(rise).unwrap_or(0);
+        let gap = ((difficulty * max_gap as f64).round() as usize + rng.gen_range(0, 2))
+            .saturating_sub(1)
+            .min(max_gap)
+            .max(if target == floor && max_gap > 0 { 1 } else { 0 });
+
+        let longest = 2 + ((1.0 - difficulty) * 5.0).round() as usize;
+        let len = rng.gen_range(2, longest + 1);
+        if end + gap + len > params.width - 1 {
+            break;
+        }
+        segments.push(Segment { x: end + gap, len: len, floor: target });
+    }
+
+    for segment in &segments {
+        let tile = if segment.floor == ground {
+            legend.ground
+        } else {
+            legend.platforms[rng.gen_range(0, legend.platforms.len())]
+        };
+        for x in segment.x..segment.x + segment.len {
+            grid[segment.floor][x] = tile;
+        }
+    }
+
+    let first = &segments[0];
+    let last = segments.last().unwrap();
+    grid[first.floor - 1][first.x + 1] = legend.start;
+    grid[last.floor - 1][last.x + last.len - 1] = legend.exit;
+
+    // the gems float over the platforms, low enough to be caught by jumping:
+    // one tile up, or two when the player can jump onto a step
+    let gem_height = if max_rise > 0 { 2 } else { 1 };
+    let mut spots: Vec<(usize, usize)> = Vec::new();
+    for segment in &segments {
+        for x in segment.x..segment.x + segment.len {
+            for y in 1..gem_height + 1 {
+                if segment.floor >= y && grid[segment.floor - y][x] == legend.blank &&
+                    grid[segment.floor - 1][x] == legend.blank {
+                    spots.push((x, segment.floor - y));
+                }
+            }
+        }
+    }
+    rng.shuffle(&mut spots);
+    for &(x, y) in spots.iter().take(params.gems) {
+        grid[y][x] = legend.gem;
+    }
+
+    let mut properties = vec![
+        Property::new("name", &format!("Generated level {}", seed), 0),
+        Property::new("seed", &seed.to_string(), 0),
+    ];
+    if params.tile_width != DEFAULT_TILE_WIDTH {
+        properties.push(Property::new("tile_width", &params.tile_width.to_string(), 0));
+    }
+    if params.tile_height != DEFAULT_TILE_HEIGHT {
+        properties.push(Property::new("tile_height", &params.tile_height.to_string(), 0));
+    }
+    if params.gravity != PLAYER_GRAVITY_ACCEL {
+        properties.push(Property::new("gravity", &params.gravity.to_string(), 0));
+    }
+
+    let file = LevelFile {
+        properties: properties,
+        tiles: grid.into_iter().map(|row| row.into_iter().collect()).collect(),
+        tiles_line: 0,
+        variants: Vec::new(),
+        variants_line: 0,
+        layers: Vec::new(),
+        platforms: Vec::new(),
+        comments: Vec::new(),
+        crlf: false,
+    };
+
+    // read the text back to get the line numbers right
+    let mut text = Vec::new();
+    file.write(&mut text).map_err(|e| e.to_string())?;
+    LevelFile::parse(&String::from_utf8_lossy(&text)).map_err(|e| e.to_string())
+}
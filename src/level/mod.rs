@@ -0,0 +1,70 @@
+// src/level/mod.rs
+
+pub mod campaign;
+pub mod file;
+pub mod generate;
+pub mod physics;
+pub mod tiled;
+pub mod tileset;
+pub mod validate;
+
+use phi::gfx::TileCollision;
+
+use std::fmt;
+
+/// Collision named in the tilesets and the Tiled maps, `None` for the
+/// unknown names.
+pub fn parse_collision(name: &str) -> Option<TileCollision> {
+    match name {
+        "passable" => Some(TileCollision::Passable),
+        "platform" => Some(TileCollision::Platform),
+        "impassable" => Some(TileCollision::Impassable),
+        _ => None,
+    }
+}
+
+/// Name of a collision, the inverse of `parse_collision`.
+pub fn collision_name(collision: TileCollision) -> &'static str {
+    match collision {
+        TileCollision::Passable => "passable",
+        TileCollision::Platform => "platform",
+        TileCollision::Impassable => "impassable",
+    }
+}
+
+/// How much a collision hinders the motion, the most restrictive one wins
+/// where tiles overlap.
+pub fn collision_rank(collision: TileCollision) -> u8 {
+    match collision {
+        TileCollision::Passable => 0,
+        TileCollision::Platform => 1,
+        TileCollision::Impassable => 2,
+    }
+}
+
+/// Error found while reading a level, `line` starts from 1 and is 0 when the
+/// error doesn't refer to a specific line.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LevelError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl LevelError {
+    pub fn new(line: usize, message: &str) -> LevelError {
+        LevelError {
+            line: line,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.line > 0 {
+            write!(f, "line {}: {}", self.line, self.message)
+        } else {
+            write!(f, "{}", self.message)
+        }
+    }
+}
@@ -0,0 +1,63 @@
+// src/level/physics.rs
+
+// Motion constants of the player, shared by the game and by the level
+// generator which must know how far the player can jump.
+
+// horizontal movements
+pub const PLAYER_MOVE_ACCEL: f32 = 13000.0_f32;
+pub const PLAYER_MAX_SPEED: f32 = 1750.0_f32;
+pub const PLAYER_GROUND_DRAG: f32 = 0.48_f32;
+pub const PLAYER_AIR_DRAG: f32 = 0.58_f32;
+
+// vertical movements
+pub const PLAYER_MAX_JUMP_TIME: f32 = 0.35_f32;
+pub const PLAYER_JUMP_LAUNCH_VEL: f32 = -3500.0_f32;
+pub const PLAYER_GRAVITY_ACCEL: f32 = 3400.0_f32;
+pub const PLAYER_MAX_FALL_SPEED: f32 = 550.0_f32;
+pub const PLAYER_JUMP_POWER: f32 = 0.14_f32;
+
+/// Vertical speed of the player `jump_time` seconds into a jump.
+pub fn jump_velocity(jump_time: f32) -> f32 {
+    PLAYER_JUMP_LAUNCH_VEL * (1.0f32 - (jump_time / PLAYER_MAX_JUMP_TIME).powf(PLAYER_JUMP_POWER))
+}
+
+/// Path of the feet of a player who jumps from standing still, holding
+/// right and up, with steps of `dt` seconds.
+///
+/// The points are the distance covered to the right and the height above the
+/// take off point, in pixels. The path ends when the player is `depth` pixels
+/// below the take off point, or after 10 seconds when `gravity` is too weak
+/// to bring the player down.
+pub fn jump_arc(gravity: f32, dt: f32, depth: f64) -> Vec<(f64, f64)> {
+    let (mut x, mut y) = (0.0f64, 0.0f64);
+    let (mut vx, mut vy) = (0.0f32, 0.0f32);
+    let mut jump_time = 0.0f32;
+    let mut on_ground = true;
+    let mut arc = Vec::new();
+
+    // the same steps as `Player::update`, without the collisions
+    let steps = (10.0 / dt) as usize;
+    while y > -depth && arc.len() < steps {
+        vx += PLAYER_MOVE_ACCEL * dt;
+        vy = (vy + gravity * dt).max(-PLAYER_MAX_FALL_SPEED).min(PLAYER_MAX_FALL_SPEED);
+
+        if on_ground || jump_time > 0.0 {
+            jump_time += dt;
+        }
+        if 0.0 < jump_time && jump_time <= PLAYER_MAX_JUMP_TIME {
+            vy = jump_velocity(jump_time);
+        } else {
+            jump_time = 0.0;
+        }
+
+        vx *= if on_ground { PLAYER_GROUND_DRAG } else { PLAYER_AIR_DRAG };
+        vx = vx.max(-PLAYER_MAX_SPEED).min(PLAYER_MAX_SPEED);
+        on_ground = false;
+
+        x += vx as f64 * dt as f64;
+        y -= vy as f64 * dt as f64;
+        arc.push((x, y));
+    }
+
+    arc
+}
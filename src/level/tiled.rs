@@ -0,0 +1,718 @@
+// src/level/tiled.rs
+
+use glm;
+
+use level::{collision_name, collision_rank, parse_collision, LevelError};
+use level::file::{LevelFile, LevelMeta, Property, DEFAULT_TILE_HEIGHT, DEFAULT_TILE_WIDTH};
+use level::tileset::{TileDef, Tileset};
+use phi::data::Rectangle;
+use phi::gfx::TileCollision;
+
+use serde_json::Value;
+
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::{Component, Path, PathBuf};
+
+use xml::common::Position;
+use xml::reader::{EventReader, XmlEvent};
+
+// the high bits of a gid are the flip flags, which are not supported
+const GID_MASK: u32 = 0x0fff_ffff;
+
+/// A map exported by the Tiled editor, either in the JSON or in the TMX
+/// format. Only orthogonal, finite maps with CSV encoded layers are read.
+///
+/// The map properties are the same as the ones in the `[level]` section of
+/// the text levels. The tiles get their collision from the `collision`
+/// property (`passable`, `platform` or `impassable`) and the objects are
+/// recognized by their type: `start`, `exit`, `gem`, `enemy` and
+/// `checkpoint`. The `kind` property of the enemies picks their sprites like
+/// the letters `A` to `D` of the text levels, `A` by default. The moving
+/// platforms of the text levels are not supported, `platform` objects are
+/// ignored.
+///
+/// The tile layers are flattened into the tiles the player collides with,
+/// except the decoration layers whose `layer` property is `background` or
+/// `foreground`, see `TileLayer::depth`.
+pub struct TiledMap {
+    pub width: usize,
+    pub height: usize,
+    pub tile_width: f64,
+    pub tile_height: f64,
+    pub properties: Vec<Property>,
+    pub tilesets: Vec<TiledTileset>,
+
+    /// tile layers, the bottom one first
+    pub layers: Vec<TileLayer>,
+    pub objects: Vec<TiledObject>,
+}
+
+pub struct TiledTileset {
+    pub first_gid: u32,
+    pub tile_width: f64,
+    pub tile_height: f64,
+    pub margin: f64,
+    pub spacing: f64,
+    pub columns: u32,
+
+    /// spritesheet holding every tile, `None` for a collection of images
+    pub image: Option<String>,
+    pub tiles: Vec<TiledTile>,
+}
+
+/// Tile of a tileset with its own image or properties.
+pub struct TiledTile {
+    pub id: u32,
+    pub image: Option<String>,
+    pub properties: Vec<Property>,
+}
+
+pub struct TileLayer {
+    pub name: String,
+    pub visible: bool,
+
+    /// one gid per tile, row by row, 0 for the empty cells
+    pub gids: Vec<u32>,
+    pub properties: Vec<Property>,
+}
+
+pub struct TiledObject {
+    pub name: String,
+    pub kind: String,
+    pub x: f64,
+    pub y: f64,
+    pub w: f64,
+    pub h: f64,
+
+    /// the tile objects are anchored to their bottom left corner
+    pub gid: u32,
+    pub properties: Vec<Property>,
+}
+
+impl TiledMap {
+    pub fn load(path: &str) -> Result<TiledMap, LevelError> {
+        let text = read(path)?;
+        let dir = Path::new(path).parent().unwrap_or(Path::new(""));
+
+        if path.ends_with(".tmx") {
+            TiledMap::from_xml(&parse_xml(&text)?, dir)
+        } else {
+            let root: Value = ::serde_json::from_str(&text)
+                .map_err(|e| LevelError::new(e.line(), &e.to_string()))?;
+            TiledMap::from_json(&root, dir)
+        }
+    }
+
+    /// Metadata built from the map properties and the size of the tiles.
+    pub fn meta(&self) -> Result<LevelMeta, LevelError> {
+        let mut meta = LevelMeta::from_properties(&self.properties)?;
+        meta.tile_width = self.tile_width;
+        meta.tile_height = self.tile_height;
+        Ok(meta)
+    }
+
+    /// Tileset of `gid` and the id of the tile inside it.
+    pub fn tile(&self, gid: u32) -> Option<(&TiledTileset, u32)> {
+        let gid = gid & GID_MASK;
+        self.tilesets.iter()
+            .filter(|t| t.first_gid <= gid)
+            .max_by_key(|t| t.first_gid)
+            .map(|t| (t, gid - t.first_gid))
+    }
+
+    /// The map in the text format, with the tiles of `tileset` which have the
+    /// collision and the image of the flattened collision layers.
+    ///
+    /// The cells whose image no tile of that collision has, the ones cut from
+    /// a spritesheet say, get the first tile of that collision and a warning
+    /// is logged for each such image. The decoration layers are lost, and
+    /// each object replaces the tile under its center. The enemies keep their
+    /// kind when `tileset` has an enemy tile of that character.
+    pub fn to_text(&self, tileset: &Tileset) -> Result<LevelFile, LevelError> {
+        let blank = symbol(tileset, "blank", |t| t.collision == TileCollision::Passable &&
+                           t.sprites.is_empty() && t.flags == Default::default())?;
+
+        // the most restrictive collision and the image drawn on top, `None`
+        // for the tiles without an image file of their own
+        let mut cells: Vec<(TileCollision, Option<String>)> =
+            vec![(TileCollision::Passable, None); self.width * self.height];
+        let layers = self.layers.iter()
+            .filter(|l| l.visible && l.depth().unwrap_or(LayerDepth::Collision) == LayerDepth::Collision);
+        for layer in layers {
+            for (i, &gid) in layer.gids.iter().enumerate().filter(|&(_, &gid)| gid != 0) {
+                let tile = self.tile(gid);
+                let collision = tile
+                    .and_then(|(tileset, id)| tileset.property(id, "collision"))
+                    .and_then(parse_collision)
+                    .unwrap_or(TileCollision::Passable);
+                if collision_rank(collision) > collision_rank(cells[i].0) {
+                    cells[i].0 = collision;
+                }
+                cells[i].1 = tile
+                    .and_then(|(tileset, id)| tileset.source(id))
+                    .and_then(|(image, region)| if region.is_none() { Some(image) } else { None });
+            }
+        }
+
+        // the tile picked for each collision and image met so far
+        let mut picked: Vec<(TileCollision, Option<String>, char)> = Vec::new();
+        let mut grid: Vec<Vec<char>> = Vec::new();
+        for row in cells.chunks(self.width.max(1)) {
+            let mut symbols = Vec::new();
+            for &(collision, ref image) in row {
+                if collision == TileCollision::Passable && image.is_none() {
+                    symbols.push(blank);
+                    continue;
+                }
+                if let Some(&(_, _, c)) = picked.iter().find(|p| p.0 == collision && p.1 == *image) {
+                    symbols.push(c);
+                    continue;
+                }
+
+                let candidates = || tileset.tiles.iter()
+                    .filter(|t| t.collision == collision && t.flags == Default::default());
+                let drawn = image.as_ref().and_then(|image| candidates().find(|t| {
+                    t.sprites.iter()
+                        .chain(t.masks.iter().flat_map(|&(_, ref images)| images))
+                        .any(|sprite| normalize(sprite) == normalize(image))
+                }));
+                let c = match drawn {
+                    Some(t) => t.symbol,
+                    None => {
+                        let c = match candidates().next() {
+                            Some(t) => t.symbol,
+                            None => return Err(LevelError::new(0, &format!(
+                                "the tileset has no {} tile", collision_name(collision)))),
+                        };
+                        warn!("no {} tile has the image {}, using '{}'",
+                              collision_name(collision),
+                              image.as_ref().map_or("of a spritesheet", |i| i.as_str()), c);
+                        c
+                    }
+                };
+                picked.push((collision, image.clone(), c));
+                symbols.push(c);
+            }
+            grid.push(symbols);
+        }
+
+        for object in &self.objects {
+            let tile = match object.kind.as_str() {
+                "start" => symbol(tileset, "start", |t| t.flags.start)?,
+                "exit" => symbol(tileset, "exit", |t| t.flags.exit)?,
+                "gem" => symbol(tileset, "gem", |t| t.flags.gem)?,
+                "checkpoint" => symbol(tileset, "checkpoint", |t| t.flags.checkpoint)?,
+                "enemy" => {
+                    let kind = object.properties.iter().rev()
+                        .find(|p| p.key == "kind")
+                        .and_then(|p| p.value.chars().next())
+                        .unwrap_or('A');
+                    match tileset.get(kind) {
+                        Some(t) if t.flags.enemy => kind,
+                        _ => symbol(tileset, "enemy", |t| t.flags.enemy)?,
+                    }
+                },
+                _ => continue,
+            };
+
+            let center = object.center();
+            let xth = (center.x / self.tile_width).floor();
+            let yth = (center.y / self.tile_height).floor();
+            if xth < 0.0 || yth < 0.0 || xth >= self.width as f64 || yth >= self.height as f64 {
+                return Err(LevelError::new(0, &format!(
+                    "the object '{}' of type '{}' is outside of the map", object.name, object.kind)));
+            }
+            grid[yth as usize][xth as usize] = tile;
+        }
+
+        let mut properties: Vec<Property> = self.properties.iter()
+            .filter(|p| p.key != "tileset" && p.key != "tile_width" && p.key != "tile_height")
+            .cloned()
+            .collect();
+        if self.tile_width != DEFAULT_TILE_WIDTH {
+            properties.push(Property::new("tile_width", &self.tile_width.to_string(), 0));
+        }
+        if self.tile_height != DEFAULT_TILE_HEIGHT {
+            properties.push(Property::new("tile_height", &self.tile_height.to_string(), 0));
+        }
+
+        Ok(LevelFile {
+            properties: properties,
+            tiles: grid.into_iter().map(|row| row.into_iter().collect()).collect(),
+            tiles_line: 0,
+            variants: Vec::new(),
+            variants_line: 0,
+            layers: Vec::new(),
+            platforms: Vec::new(),
+            comments: Vec::new(),
+            crlf: false,
+        })
+    }
+
+    fn check(&self) -> Result<(), LevelError> {
+        for layer in &self.layers {
+            if layer.gids.len() != self.width * self.height {
+                return Err(LevelError::new(0, &format!(
+                    "layer '{}' has {} tiles instead of {}",
+                    layer.name, layer.gids.len(), self.width * self.height)));
+            }
+        }
+        Ok(())
+    }
+
+    fn from_json(root: &Value, dir: &Path) -> Result<TiledMap, LevelError> {
+        if root["infinite"].as_bool() == Some(true) {
+            return Err(LevelError::new(0, "infinite maps are not supported"));
+        }
+
+        let mut map = TiledMap {
+            width: json_u64(root, "width")? as usize,
+            height: json_u64(root, "height")? as usize,
+            tile_width: json_f64(root, "tilewidth")?,
+            tile_height: json_f64(root, "tileheight")?,
+            properties: json_properties(root),
+            tilesets: Vec::new(),
+            layers: Vec::new(),
+            objects: Vec::new(),
+        };
+
+        for tileset in root["tilesets"].as_array().unwrap_or(&Vec::new()) {
+            let first_gid = json_u64(tileset, "firstgid")? as u32;
+            map.tilesets.push(match tileset["source"].as_str() {
+                Some(source) => TiledTileset::load(&dir.join(source), first_gid)?,
+                None => TiledTileset::from_json(tileset, first_gid, dir)?,
+            });
+        }
+
+        map.json_layers(root, dir)?;
+        map.check()?;
+        Ok(map)
+    }
+
+    // layers may be nested into groups
+    fn json_layers(&mut self, parent: &Value, dir: &Path) -> Result<(), LevelError> {
+        for layer in parent["layers"].as_array().unwrap_or(&Vec::new()) {
+            match layer["type"].as_str() {
+                Some("tilelayer") => {
+                    let data = layer["data"].as_array().ok_or_else(|| LevelError::new(
+                        0, "only the CSV layer format is supported"))?;
+                    let gids = data.iter().map(|gid| {
+                        gid.as_u64()
+                            .filter(|&gid| gid <= u32::max_value() as u64)
+                            .map(|gid| gid as u32)
+                            .ok_or_else(|| LevelError::new(0, &format!("invalid tile {}", gid)))
+                    }).collect::<Result<Vec<u32>, LevelError>>()?;
+
+                    self.layers.push(TileLayer {
+                        name: layer["name"].as_str().unwrap_or("").to_string(),
+                        visible: layer["visible"].as_bool().unwrap_or(true),
+                        gids: gids,
+                        properties: json_properties(layer),
+                    });
+                },
+                Some("objectgroup") => {
+                    for object in layer["objects"].as_array().unwrap_or(&Vec::new()) {
+                        self.objects.push(TiledObject::from_json(object));
+                    }
+                },
+                Some("group") => self.json_layers(layer, dir)?,
+                _ => {},
+            }
+        }
+        Ok(())
+    }
+
+    fn from_xml(root: &Element, dir: &Path) -> Result<TiledMap, LevelError> {
+        if root.name != "map" {
+            return Err(LevelError::new(0, "the root element is not <map>"));
+        }
+        if root.attr("infinite") == Some("1") {
+            return Err(LevelError::new(0, "infinite maps are not supported"));
+        }
+
+        let mut map = TiledMap {
+            width: xml_parse(root, "width")?,
+            height: xml_parse(root, "height")?,
+            tile_width: xml_parse(root, "tilewidth")?,
+            tile_height: xml_parse(root, "tileheight")?,
+            properties: xml_properties(root),
+            tilesets: Vec::new(),
+            layers: Vec::new(),
+            objects: Vec::new(),
+        };
+
+        for tileset in root.children.iter().filter(|e| e.name == "tileset") {
+            let first_gid = xml_parse::<u32>(tileset, "firstgid")?;
+            map.tilesets.push(match tileset.attr("source") {
+                Some(source) => TiledTileset::load(&dir.join(source), first_gid)?,
+                None => TiledTileset::from_xml(tileset, first_gid, dir)?,
+            });
+        }
+
+        map.xml_layers(root)?;
+        map.check()?;
+        Ok(map)
+    }
+
+    fn xml_layers(&mut self, parent: &Element) -> Result<(), LevelError> {
+        for layer in &parent.children {
+            match layer.name.as_str() {
+                "layer" => {
+                    let data = layer.child("data").ok_or_else(|| LevelError::new(
+                        0, "layer without data"))?;
+                    if data.attr("encoding") != Some("csv") {
+                        return Err(LevelError::new(0, "only the CSV layer format is supported"));
+                    }
+
+                    let mut gids = Vec::new();
+                    for gid in data.text.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+                        gids.push(gid.parse::<u32>().map_err(|_| LevelError::new(
+                            0, &format!("invalid tile '{}'", gid)))?);
+                    }
+
+                    self.layers.push(TileLayer {
+                        name: layer.attr("name").unwrap_or("").to_string(),
+                        visible: layer.attr("visible") != Some("0"),
+                        gids: gids,
+                        properties: xml_properties(layer),
+                    });
+                },
+                "objectgroup" => {
+                    for object in layer.children.iter().filter(|e| e.name == "object") {
+                        self.objects.push(TiledObject::from_xml(object)?);
+                    }
+                },
+                "group" => self.xml_layers(layer)?,
+                _ => {},
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Where a tile layer is drawn.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LayerDepth {
+    /// decoration behind the tiles
+    Background,
+
+    /// the tiles the player collides with
+    Collision,
+
+    /// decoration over the player
+    Foreground,
+}
+
+impl TileLayer {
+    /// Depth named by the `layer` property, the layers without one are
+    /// collision layers. `Err` holds an unknown name.
+    pub fn depth(&self) -> Result<LayerDepth, String> {
+        match self.properties.iter().find(|p| p.key == "layer").map(|p| p.value.as_str()) {
+            None | Some("collision") => Ok(LayerDepth::Collision),
+            Some("background") => Ok(LayerDepth::Background),
+            Some("foreground") => Ok(LayerDepth::Foreground),
+            Some(other) => Err(other.to_string()),
+        }
+    }
+}
+
+impl TiledTileset {
+    // external tileset, in the format given by its extension
+    fn load(path: &Path, first_gid: u32) -> Result<TiledTileset, LevelError> {
+        let name = path.to_string_lossy().into_owned();
+        let text = read(&name)?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+
+        if name.ends_with(".tsx") {
+            TiledTileset::from_xml(&parse_xml(&text)?, first_gid, dir)
+        } else {
+            let root: Value = ::serde_json::from_str(&text)
+                .map_err(|e| LevelError::new(0, &format!("{}: {}", name, e)))?;
+            TiledTileset::from_json(&root, first_gid, dir)
+        }
+    }
+
+    fn from_json(root: &Value, first_gid: u32, dir: &Path) -> Result<TiledTileset, LevelError> {
+        let mut tileset = TiledTileset {
+            first_gid: first_gid,
+            tile_width: json_f64(root, "tilewidth")?,
+            tile_height: json_f64(root, "tileheight")?,
+            margin: root["margin"].as_f64().unwrap_or(0.0),
+            spacing: root["spacing"].as_f64().unwrap_or(0.0),
+            columns: root["columns"].as_u64().unwrap_or(0) as u32,
+            image: root["image"].as_str().map(|image| join(dir, image)),
+            tiles: Vec::new(),
+        };
+
+        for tile in root["tiles"].as_array().unwrap_or(&Vec::new()) {
+            tileset.tiles.push(TiledTile {
+                id: json_u64(tile, "id")? as u32,
+                image: tile["image"].as_str().map(|image| join(dir, image)),
+                properties: json_properties(tile),
+            });
+        }
+
+        // before Tiled 1.2 the tile properties had their own object
+        if let Some(properties) = root["tileproperties"].as_object() {
+            for (id, properties) in properties {
+                let id = id.parse::<u32>().map_err(|_| LevelError::new(
+                    0, &format!("invalid tile id '{}'", id)))?;
+                let properties: Vec<Property> = properties.as_object().map_or(Vec::new(), |map| {
+                    map.iter().map(|(k, v)| Property::new(k, &json_string(v), 0)).collect()
+                });
+                match tileset.tiles.iter().position(|t| t.id == id) {
+                    Some(i) => tileset.tiles[i].properties.extend(properties),
+                    None => tileset.tiles.push(TiledTile {
+                        id: id,
+                        image: None,
+                        properties: properties,
+                    }),
+                }
+            }
+        }
+
+        Ok(tileset)
+    }
+
+    fn from_xml(root: &Element, first_gid: u32, dir: &Path) -> Result<TiledTileset, LevelError> {
+        let mut tileset = TiledTileset {
+            first_gid: first_gid,
+            tile_width: xml_parse(root, "tilewidth")?,
+            tile_height: xml_parse(root, "tileheight")?,
+            margin: root.attr("margin").and_then(|v| v.parse().ok()).unwrap_or(0.0),
+            spacing: root.attr("spacing").and_then(|v| v.parse().ok()).unwrap_or(0.0),
+            columns: root.attr("columns").and_then(|v| v.parse().ok()).unwrap_or(0),
+            image: root.child("image").and_then(|i| i.attr("source")).map(|image| join(dir, image)),
+            tiles: Vec::new(),
+        };
+
+        for tile in root.children.iter().filter(|e| e.name == "tile") {
+            tileset.tiles.push(TiledTile {
+                id: xml_parse(tile, "id")?,
+                image: tile.child("image").and_then(|i| i.attr("source")).map(|image| join(dir, image)),
+                properties: xml_properties(tile),
+            });
+        }
+
+        Ok(tileset)
+    }
+
+    /// Image of the tile `id` and its region, `None` for the whole image.
+    pub fn source(&self, id: u32) -> Option<(String, Option<Rectangle>)> {
+        if let Some(image) = self.tiles.iter().find(|t| t.id == id).and_then(|t| t.image.clone()) {
+            return Some((image, None));
+        }
+
+        let image = match self.image {
+            Some(ref image) if self.columns > 0 => image.clone(),
+            _ => return None,
+        };
+
+        let (column, row) = ((id % self.columns) as f64, (id / self.columns) as f64);
+        Some((image, Some(Rectangle {
+            x: self.margin + column * (self.tile_width + self.spacing),
+            y: self.margin + row * (self.tile_height + self.spacing),
+            w: self.tile_width,
+            h: self.tile_height,
+        })))
+    }
+
+    /// Value of the property `key` of the tile `id`.
+    pub fn property(&self, id: u32, key: &str) -> Option<&str> {
+        self.tiles.iter()
+            .find(|t| t.id == id)
+            .and_then(|t| t.properties.iter().rev().find(|p| p.key == key))
+            .map(|p| p.value.as_str())
+    }
+}
+
+impl TiledObject {
+    // the kind is `class` since Tiled 1.9, `type` before; the name is used
+    // when both are missing
+    fn with_kind(mut self) -> TiledObject {
+        if self.kind.is_empty() {
+            self.kind = self.name.to_lowercase();
+        }
+        self
+    }
+
+    fn from_json(object: &Value) -> TiledObject {
+        TiledObject {
+            name: object["name"].as_str().unwrap_or("").to_string(),
+            kind: object["type"].as_str()
+                .or_else(|| object["class"].as_str())
+                .unwrap_or("").to_string(),
+            x: object["x"].as_f64().unwrap_or(0.0),
+            y: object["y"].as_f64().unwrap_or(0.0),
+            w: object["width"].as_f64().unwrap_or(0.0),
+            h: object["height"].as_f64().unwrap_or(0.0),
+            gid: object["gid"].as_u64().unwrap_or(0) as u32,
+            properties: json_properties(object),
+        }.with_kind()
+    }
+
+    fn from_xml(object: &Element) -> Result<TiledObject, LevelError> {
+        let number = |key: &str| -> Result<f64, LevelError> {
+            match object.attr(key) {
+                Some(_) => xml_parse(object, key),
+                None => Ok(0.0),
+            }
+        };
+
+        Ok(TiledObject {
+            name: object.attr("name").unwrap_or("").to_string(),
+            kind: object.attr("type").or_else(|| object.attr("class")).unwrap_or("").to_string(),
+            x: number("x")?,
+            y: number("y")?,
+            w: number("width")?,
+            h: number("height")?,
+            gid: object.attr("gid").and_then(|v| v.parse().ok()).unwrap_or(0),
+            properties: xml_properties(object),
+        }.with_kind())
+    }
+
+    /// Center of the object in pixels.
+    pub fn center(&self) -> glm::Vector2<f64> {
+        let top = if self.gid != 0 { self.y - self.h } else { self.y };
+        glm::Vector2::new(self.x + self.w / 2.0, top + self.h / 2.0)
+    }
+}
+
+// the first tile of `tileset` matching `f`
+fn symbol<F>(tileset: &Tileset, what: &str, f: F) -> Result<char, LevelError>
+    where F: Fn(&TileDef) -> bool {
+    tileset.tiles.iter().find(|t| f(t)).map(|t| t.symbol)
+        .ok_or_else(|| LevelError::new(0, &format!("the tileset has no {} tile", what)))
+}
+
+// `path` without its `.` and `..` components, to compare the images of the
+// maps, relative to the map, with the ones of the tilesets
+fn normalize(path: &str) -> PathBuf {
+    let mut normal = PathBuf::new();
+    for component in Path::new(path).components() {
+        match component {
+            Component::CurDir => {},
+            Component::ParentDir if normal.file_name().is_some() => { normal.pop(); },
+            _ => normal.push(component.as_os_str()),
+        }
+    }
+    normal
+}
+
+fn read(path: &str) -> Result<String, LevelError> {
+    let mut text = String::new();
+    File::open(path)
+        .and_then(|mut f| f.read_to_string(&mut text))
+        .map_err(|e| LevelError::new(0, &format!("{}: {}", path, e)))?;
+    Ok(text)
+}
+
+// paths in the Tiled files are relative to the file itself
+fn join(dir: &Path, path: &str) -> String {
+    dir.join(path).to_string_lossy().into_owned()
+}
+
+fn json_u64(value: &Value, key: &str) -> Result<u64, LevelError> {
+    value[key].as_u64().ok_or_else(|| LevelError::new(
+        0, &format!("missing or invalid '{}'", key)))
+}
+
+fn json_f64(value: &Value, key: &str) -> Result<f64, LevelError> {
+    value[key].as_f64().ok_or_else(|| LevelError::new(
+        0, &format!("missing or invalid '{}'", key)))
+}
+
+// properties are a list of `{ name, type, value }` objects since Tiled 1.2,
+// a plain object before
+fn json_properties(value: &Value) -> Vec<Property> {
+    match value["properties"] {
+        Value::Array(ref list) => list.iter().map(|p| {
+            Property::new(p["name"].as_str().unwrap_or(""), &json_string(&p["value"]), 0)
+        }).collect(),
+        Value::Object(ref map) => map.iter().map(|(k, v)| {
+            Property::new(k, &json_string(v), 0)
+        }).collect(),
+        _ => Vec::new(),
+    }
+}
+
+// property values may be strings, numbers or booleans
+fn json_string(value: &Value) -> String {
+    match *value {
+        Value::String(ref s) => s.clone(),
+        ref other => other.to_string(),
+    }
+}
+
+/// Minimal XML tree, enough for the TMX and TSX files.
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Element>,
+    text: String,
+}
+
+impl Element {
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attributes.iter().find(|a| a.0 == name).map(|a| a.1.as_str())
+    }
+
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|e| e.name == name)
+    }
+}
+
+fn parse_xml(text: &str) -> Result<Element, LevelError> {
+    let mut stack: Vec<Element> = Vec::new();
+
+    for event in EventReader::from_str(text) {
+        let event = event.map_err(|e| LevelError::new(
+            e.position().row as usize + 1, &e.msg().to_string()))?;
+
+        match event {
+            XmlEvent::StartElement { name, attributes, .. } => {
+                stack.push(Element {
+                    name: name.local_name,
+                    attributes: attributes.into_iter()
+                        .map(|a| (a.name.local_name, a.value))
+                        .collect(),
+                    children: Vec::new(),
+                    text: String::new(),
+                });
+            },
+            XmlEvent::EndElement { .. } => {
+                let element = stack.pop().unwrap();
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(element),
+                    None => return Ok(element),
+                }
+            },
+            XmlEvent::Characters(text) | XmlEvent::CData(text) => {
+                if let Some(element) = stack.last_mut() {
+                    element.text.push_str(&text);
+                }
+            },
+            _ => {},
+        }
+    }
+
+    Err(LevelError::new(0, "empty XML document"))
+}
+
+fn xml_parse<T: ::std::str::FromStr>(element: &Element, key: &str) -> Result<T, LevelError> {
+    element.attr(key)
+        .and_then(|v| v.parse::<T>().ok())
+        .ok_or_else(|| LevelError::new(0, &format!(
+            "missing or invalid '{}' in <{}>", key, element.name)))
+}
+
+// multiline values are stored in the text of the element
+fn xml_properties(element: &Element) -> Vec<Property> {
+    element.child("properties").map_or(Vec::new(), |properties| {
+        properties.children.iter().filter(|p| p.name == "property").map(|p| {
+            Property::new(p.attr("name").unwrap_or(""),
+                          p.attr("value").unwrap_or(&p.text), 0)
+        }).collect()
+    })
+}
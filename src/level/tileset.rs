@@ -0,0 +1,331 @@
+// src/level/tileset.rs
+
+use level::{parse_collision, LevelError};
+use level::file::LevelMeta;
+use phi::gfx::TileCollision;
+
+use std::fs::File;
+use std::io::prelude::*;
+
+/// Tileset used by the levels without a `tileset` property.
+pub const DEFAULT_TILESET: &'static str = "assets/tilesets/default.txt";
+
+/// Variants of a tile, as many as the digits of the `[variants]` section of
+/// the level files.
+pub const MAX_VARIANTS: usize = 36;
+
+/// Special meaning of a tile besides its look and collision.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TileFlags {
+    /// the player starts standing on the bottom of the tile
+    pub start: bool,
+
+    /// touching the tile completes the level
+    pub exit: bool,
+
+    /// a gem floats in the center of the tile
+    pub gem: bool,
+
+    /// an enemy, whose kind is the tile character, starts on the tile
+    pub enemy: bool,
+
+    /// touching the tile moves the place where the player respawns there
+    pub checkpoint: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct TileDef {
+    pub symbol: char,
+    pub collision: TileCollision,
+
+    /// images of the tile, one is picked at random when there are more, no
+    /// image is drawn when empty
+    pub sprites: Vec<String>,
+    pub flags: TileFlags,
+
+    /// 4 or 8 to pick the images from the neighbours of the tile, see
+    /// `Tileset::neighbour_mask`, 0 to always pick from `sprites`
+    pub autotile: u32,
+
+    /// images of the tile by neighbour mask, the masks missing from the table
+    /// use `sprites`
+    pub masks: Vec<(u32, Vec<String>)>,
+
+    /// other tiles counted as the same tile in the neighbour masks
+    pub connects: Vec<char>,
+}
+
+/// Meaning of the characters of the text levels.
+///
+/// The file has a section for each character, its name between square
+/// brackets, followed by `key = value` lines:
+///
+/// ```text
+/// [#]
+/// collision = impassable
+/// sprite = assets/tiles/blocka{}.png
+/// variants = 7
+/// ```
+///
+/// `sprite` may be repeated to list the variants explicitly, or contain `{}`
+/// which is replaced by the numbers from 0 to `variants` - 1.
+///
+/// With `autotile = 4` or `autotile = 8` the image depends on the neighbours
+/// of the tile: `mask N = image` gives the images of the tile when its
+/// neighbour mask is `N`. It may be repeated, and expands `{}` like `sprite`,
+/// to draw a random variant among several images, for the inner tiles say.
+///
+/// ```text
+/// [#]
+/// collision = impassable
+/// autotile = 4
+/// connects = ~
+/// sprite = assets/tiles/blocka0.png
+/// mask 15 = assets/tiles/blocka{}.png
+/// ```
+#[derive(Clone, Debug)]
+pub struct Tileset {
+    pub tiles: Vec<TileDef>,
+}
+
+impl Tileset {
+    pub fn load(path: &str) -> Result<Tileset, LevelError> {
+        let mut text = String::new();
+        File::open(path)
+            .and_then(|mut f| f.read_to_string(&mut text))
+            .map_err(|e| LevelError::new(0, &format!("{}: {}", path, e)))?;
+
+        Tileset::parse(&text).map_err(|e| LevelError::new(
+            0, &format!("{}:{}: {}", path, e.line, e.message)))
+    }
+
+    pub fn parse(text: &str) -> Result<Tileset, LevelError> {
+        let mut tiles: Vec<TileDef> = Vec::new();
+
+        // the tile being read and the line of its section
+        let mut current: Option<(TileDef, usize)> = None;
+        let mut variants = 1;
+
+        for (i, line) in text.lines().enumerate() {
+            let lineno = i + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if line.starts_with('[') && line.ends_with(']') && line.chars().count() == 3 {
+                if let Some((tile, start)) = current.take() {
+                    tiles.push(Tileset::finish(tile, variants, start)?);
+                }
+
+                let symbol = line.chars().nth(1).unwrap();
+                if tiles.iter().any(|t| t.symbol == symbol) {
+                    return Err(LevelError::new(
+                        lineno, &format!("duplicate tile '{}'", symbol)));
+                }
+
+                current = Some((TileDef {
+                    symbol: symbol,
+                    collision: TileCollision::Passable,
+                    sprites: Vec::new(),
+                    flags: TileFlags::default(),
+                    autotile: 0,
+                    masks: Vec::new(),
+                    connects: Vec::new(),
+                }, lineno));
+                variants = 1;
+                continue;
+            }
+
+            let tile = match current {
+                Some((ref mut tile, _)) => tile,
+                None => return Err(LevelError::new(lineno, "expected a tile section like [#]")),
+            };
+
+            let mut parts = line.splitn(2, '=');
+            let key = parts.next().unwrap().trim();
+            let value = match parts.next() {
+                Some(value) => value.trim(),
+                None => return Err(LevelError::new(
+                    lineno, &format!("expected `key = value`, found '{}'", line))),
+            };
+
+            match key {
+                "collision" => {
+                    tile.collision = parse_collision(value).ok_or_else(|| LevelError::new(
+                        lineno, &format!("unknown collision '{}'", value)))?;
+                },
+                "sprite" => tile.sprites.push(value.to_string()),
+                "variants" => {
+                    variants = value.parse::<usize>().ok().filter(|&n| n > 0)
+                        .ok_or_else(|| LevelError::new(
+                            lineno, &format!("invalid variants '{}'", value)))?;
+                },
+                "flags" => {
+                    for flag in value.split(|c: char| c == ',' || c.is_whitespace())
+                        .filter(|f| !f.is_empty()) {
+                        match flag {
+                            "start" => tile.flags.start = true,
+                            "exit" => tile.flags.exit = true,
+                            "gem" => tile.flags.gem = true,
+                            "enemy" => tile.flags.enemy = true,
+                            "checkpoint" => tile.flags.checkpoint = true,
+                            _ => return Err(LevelError::new(
+                                lineno, &format!("unknown flag '{}'", flag))),
+                        }
+                    }
+                },
+                "autotile" => {
+                    tile.autotile = match value {
+                        "4" => 4,
+                        "8" => 8,
+                        _ => return Err(LevelError::new(
+                            lineno, &format!("autotile must be 4 or 8, not '{}'", value))),
+                    };
+                },
+                "connects" => tile.connects.extend(value.chars().filter(|c| !c.is_whitespace())),
+                _ if key.starts_with("mask ") => {
+                    let mask = key["mask ".len()..].trim();
+                    let mask = mask.parse::<u32>().ok().filter(|&m| m <= 0xff)
+                        .ok_or_else(|| LevelError::new(
+                            lineno, &format!("invalid mask '{}'", mask)))?;
+                    match tile.masks.iter().position(|&(m, _)| m == mask) {
+                        Some(i) => tile.masks[i].1.push(value.to_string()),
+                        None => tile.masks.push((mask, vec![value.to_string()])),
+                    }
+                },
+                _ => return Err(LevelError::new(
+                    lineno, &format!("unknown property '{}'", key))),
+            }
+        }
+
+        if let Some((tile, start)) = current.take() {
+            tiles.push(Tileset::finish(tile, variants, start)?);
+        }
+
+        Ok(Tileset {
+            tiles: tiles,
+        })
+    }
+
+    // expand the `{}` in the sprite names into the variants
+    fn finish(mut tile: TileDef, variants: usize, line: usize) -> Result<TileDef, LevelError> {
+        let sprites = ::std::mem::replace(&mut tile.sprites, Vec::new());
+        for sprite in sprites {
+            if sprite.contains("{}") {
+                for i in 0..variants {
+                    tile.sprites.push(sprite.replace("{}", &i.to_string()));
+                }
+            } else if variants > 1 {
+                return Err(LevelError::new(line, &format!(
+                    "tile '{}' has {} variants but the sprite {} has no {{}}",
+                    tile.symbol, variants, sprite)));
+            } else {
+                tile.sprites.push(sprite);
+            }
+        }
+
+        // the images of the masks may mix patterns and plain names
+        for &mut (_, ref mut images) in &mut tile.masks {
+            *images = images.iter().flat_map(|image| if image.contains("{}") {
+                (0..variants).map(|i| image.replace("{}", &i.to_string())).collect()
+            } else {
+                vec![image.clone()]
+            }).collect();
+        }
+
+        if !tile.masks.is_empty() && tile.autotile == 0 {
+            return Err(LevelError::new(line, &format!(
+                "tile '{}' has masks but no autotile", tile.symbol)));
+        }
+        let max_mask = if tile.autotile == 4 { 0x0f } else { 0xff };
+        if let Some(&(mask, _)) = tile.masks.iter().find(|&&(m, _)| m > max_mask) {
+            return Err(LevelError::new(line, &format!(
+                "tile '{}' has mask {}, at most {} with autotile = {}",
+                tile.symbol, mask, max_mask, tile.autotile)));
+        }
+
+        let most = tile.masks.iter().map(|&(_, ref images)| images.len())
+            .fold(tile.sprites.len(), ::std::cmp::max);
+        if most > MAX_VARIANTS {
+            return Err(LevelError::new(line, &format!(
+                "tile '{}' has {} variants, at most {} are allowed",
+                tile.symbol, most, MAX_VARIANTS)));
+        }
+        Ok(tile)
+    }
+
+    /// Load the tileset named by the `tileset` property of a level, or the
+    /// default one.
+    pub fn for_meta(meta: &LevelMeta) -> Result<Tileset, LevelError> {
+        Tileset::load(meta.tileset.as_ref().map_or(DEFAULT_TILESET, |s| s.as_str()))
+    }
+
+    pub fn get(&self, symbol: char) -> Option<&TileDef> {
+        self.tiles.iter().find(|t| t.symbol == symbol)
+    }
+
+    /// Neighbours of the tile at `x`, `y` of `tiles` which are the same tile
+    /// or one it connects to, the outside of the level counts as the same
+    /// tile.
+    ///
+    /// The bits are 1 above, 2 on the right, 4 below and 8 on the left, the
+    /// 8-neighbour masks add 16 above right, 32 below right, 64 below left and
+    /// 128 above left. A corner only counts when both its sides do, so that
+    /// the table needs 47 masks at most instead of 256.
+    pub fn neighbour_mask(&self, tiles: &[String], x: usize, y: usize) -> u32 {
+        let def = match tiles.get(y).and_then(|row| row.chars().nth(x)).and_then(|c| self.get(c)) {
+            Some(def) => def,
+            None => return 0,
+        };
+
+        let same = |dx: i32, dy: i32| {
+            let (nx, ny) = (x as i32 + dx, y as i32 + dy);
+            if nx < 0 || ny < 0 || ny as usize >= tiles.len() {
+                return true;
+            }
+            match tiles[ny as usize].chars().nth(nx as usize) {
+                Some(c) => c == def.symbol || def.connects.contains(&c),
+                None => true,
+            }
+        };
+
+        let sides = [(0, -1), (1, 0), (0, 1), (-1, 0)];
+        let mut mask = 0;
+        for (bit, &(dx, dy)) in sides.iter().enumerate() {
+            if same(dx, dy) {
+                mask |= 1 << bit;
+            }
+        }
+
+        if def.autotile == 8 {
+            // a corner sits between the side of its bit and the next one
+            for bit in 0..4 {
+                let (ax, ay) = sides[bit];
+                let (bx, by) = sides[(bit + 1) % 4];
+                let both = 1 << bit | 1 << ((bit + 1) % 4);
+                if mask & both == both && same(ax + bx, ay + by) {
+                    mask |= 16 << bit;
+                }
+            }
+        }
+        mask
+    }
+
+    /// Images among which the tile at `x`, `y` of `tiles` picks its own, the
+    /// variants of the level files index this list.
+    pub fn sprites_at(&self, tiles: &[String], x: usize, y: usize) -> &[String] {
+        let def = match tiles.get(y).and_then(|row| row.chars().nth(x)).and_then(|c| self.get(c)) {
+            Some(def) => def,
+            None => return &[],
+        };
+        if def.autotile == 0 {
+            return &def.sprites;
+        }
+
+        let mask = self.neighbour_mask(tiles, x, y);
+        def.masks.iter()
+            .find(|&&(m, _)| m == mask)
+            .map_or(&def.sprites, |&(_, ref images)| images)
+    }
+}
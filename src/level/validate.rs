@@ -0,0 +1,313 @@
+// src/level/validate.rs
+
+use level::file::{variant_at, LevelFile};
+use level::tiled::TiledMap;
+use level::tileset::Tileset;
+use phi::gfx::TileCollision;
+
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Severity {
+    /// the level can't be loaded or can't be completed
+    Error,
+
+    /// the level loads but probably doesn't behave as intended
+    Warning,
+}
+
+/// A problem found in a level, `line` and `column` start from 1 and are 0
+/// when the problem doesn't refer to a specific place.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn error(line: usize, column: usize, message: String) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Error,
+            line: line,
+            column: column,
+            message: message,
+        }
+    }
+
+    fn warning(line: usize, column: usize, message: String) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Warning,
+            ..Diagnostic::error(line, column, message)
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        match (self.line, self.column) {
+            (0, _) => write!(f, "{}: {}", severity, self.message),
+            (line, 0) => write!(f, "{}: {}: {}", line, severity, self.message),
+            (line, column) => write!(f, "{}:{}: {}: {}", line, column, severity, self.message),
+        }
+    }
+}
+
+/// Check the level at `path`, in the format given by its extension.
+pub fn validate_path(path: &str) -> Vec<Diagnostic> {
+    if path.ends_with(".json") || path.ends_with(".tmx") {
+        return validate_tiled(path);
+    }
+
+    let file = match LevelFile::load(path) {
+        Ok(file) => file,
+        Err(e) => return vec![Diagnostic::error(e.line, 0, e.message)],
+    };
+
+    // a level with broken metadata is still checked against the default
+    // tileset, the metadata error is reported by `validate`
+    let meta = file.meta().unwrap_or_default();
+    match Tileset::for_meta(&meta) {
+        Ok(tileset) => validate(&file, &tileset),
+        Err(e) => {
+            let line = file.properties.iter()
+                .filter(|p| p.key == "tileset")
+                .last()
+                .map_or(0, |p| p.line);
+            vec![Diagnostic::error(line, 0, e.message)]
+        }
+    }
+}
+
+/// Check a text level for the mistakes the loader doesn't catch or only
+/// catches by panicking: ragged rows, tiles missing from `tileset`, a missing
+/// or repeated start or exit, a start point or a checkpoint with nothing to
+/// stand on, decoration layers larger than the level and moving platforms
+/// which can't be stood on or leave the level.
+pub fn validate(file: &LevelFile, tileset: &Tileset) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    if let Err(e) = file.meta() {
+        diagnostics.push(Diagnostic::error(e.line, 1, e.message));
+    }
+
+    if file.tiles.is_empty() {
+        diagnostics.push(Diagnostic::error(file.tiles_line, 0, "the level has no tiles".to_string()));
+        return diagnostics;
+    }
+
+    // every row must be as wide as the first one
+    let width = file.tiles[0].chars().count();
+    let mut starts: Vec<(usize, usize)> = Vec::new();
+    let mut exits: Vec<(usize, usize)> = Vec::new();
+    let mut checkpoints: Vec<(usize, usize)> = Vec::new();
+
+    for (y, row) in file.tiles.iter().enumerate() {
+        let line = file.tiles_line + y;
+
+        let row_width = row.chars().count();
+        if row_width != width {
+            diagnostics.push(Diagnostic::error(
+                line, row_width.min(width) + 1,
+                format!("row is {} tiles wide, expected {} like the first row", row_width, width)));
+        }
+
+        for (x, c) in row.chars().enumerate() {
+            match tileset.get(c) {
+                Some(def) => {
+                    if def.flags.start {
+                        starts.push((x, y));
+                    }
+                    if def.flags.exit {
+                        exits.push((x, y));
+                    }
+                    if def.flags.checkpoint {
+                        checkpoints.push((x, y));
+                    }
+                },
+                None => diagnostics.push(Diagnostic::error(
+                    line, x + 1, format!("unknown tile '{}'", c))),
+            }
+        }
+    }
+
+    for &(name, ref found) in &[("start point", &starts), ("exit", &exits)] {
+        match found.first() {
+            None => diagnostics.push(Diagnostic::error(
+                file.tiles_line, 0, format!("missing {}", name))),
+            Some(&(first_x, first_y)) => {
+                for &(x, y) in &found[1..] {
+                    diagnostics.push(Diagnostic::error(
+                        file.tiles_line + y, x + 1,
+                        format!("duplicate {}, the first one is at {}:{}",
+                                name, file.tiles_line + first_y, first_x + 1)));
+                }
+            }
+        }
+    }
+
+    check_variants(&mut diagnostics, tileset, &file.tiles, &file.variants, file.variants_line);
+
+    // the decoration layers use the tiles of the tileset but neither their
+    // collision nor their flags, and are not drawn outside of the level
+    for layer in &file.layers {
+        if layer.rows.len() > file.tiles.len() {
+            diagnostics.push(Diagnostic::warning(
+                layer.line + file.tiles.len(), 1,
+                format!("layer has {} rows, the tiles have {}", layer.rows.len(), file.tiles.len())));
+        }
+
+        for (y, row) in layer.rows.iter().enumerate() {
+            let line = layer.line + y;
+            if row.chars().count() > width {
+                diagnostics.push(Diagnostic::warning(
+                    line, width + 1,
+                    format!("row is {} tiles wide, the tiles are {}", row.chars().count(), width)));
+            }
+
+            for (x, c) in row.chars().enumerate() {
+                match tileset.get(c) {
+                    Some(def) => if def.flags != Default::default() {
+                        diagnostics.push(Diagnostic::warning(
+                            line, x + 1,
+                            format!("the flags of tile '{}' are ignored in a layer", c)));
+                    },
+                    None => diagnostics.push(Diagnostic::error(
+                        line, x + 1, format!("unknown tile '{}'", c))),
+                }
+            }
+        }
+
+        check_variants(&mut diagnostics, tileset, &layer.rows, &layer.variants,
+                       layer.variants_line);
+    }
+
+    // the moving platforms take the collision of their tile and go from
+    // waypoint to waypoint in straight lines, staying in the level
+    match file.platforms() {
+        Err(e) => diagnostics.push(Diagnostic::error(e.line, 1, e.message)),
+        Ok(platforms) => for (platform, section) in platforms.iter().zip(&file.platforms) {
+            match tileset.get(platform.tile).map(|def| def.collision) {
+                None => diagnostics.push(Diagnostic::error(
+                    section.line, 0, format!("unknown platform tile '{}'", platform.tile))),
+                Some(TileCollision::Passable) => diagnostics.push(Diagnostic::warning(
+                    section.line, 0,
+                    format!("platform tile '{}' is passable, nothing can stand on it",
+                            platform.tile))),
+                Some(_) => {},
+            }
+
+            let path_line = section.properties.iter()
+                .filter(|p| p.key == "path")
+                .last()
+                .map_or(section.line, |p| p.line);
+            for &(x, y) in &platform.path {
+                if x + platform.width > width || y >= file.tiles.len() {
+                    diagnostics.push(Diagnostic::warning(
+                        path_line, 0, format!("waypoint {},{} is outside of the level", x, y)));
+                }
+            }
+        },
+    }
+
+    // the player appears standing on the bottom of the start tile, and of
+    // the checkpoints when respawning
+    let places = starts.iter().map(|&place| ("start point", place))
+        .chain(checkpoints.iter().map(|&place| ("checkpoint", place)));
+    for (name, (x, y)) in places {
+        let below = file.tiles.get(y + 1).and_then(|row| row.chars().nth(x));
+        let solid = match below.and_then(|c| tileset.get(c)).map(|def| def.collision) {
+            Some(TileCollision::Impassable) | Some(TileCollision::Platform) => true,
+            _ => false,
+        };
+
+        if !solid {
+            diagnostics.push(Diagnostic::warning(
+                file.tiles_line + y, x + 1,
+                format!("the {} has no floor underneath", name)));
+        }
+    }
+
+    diagnostics.sort_by_key(|d| (d.line, d.column));
+    diagnostics
+}
+
+// the variants recorded for the tiles of `tiles` must exist in the tileset,
+// `line` is the one of the first row of `variants`
+fn check_variants(diagnostics: &mut Vec<Diagnostic>, tileset: &Tileset, tiles: &[String],
+                  variants: &[String], line: usize) {
+    for (y, row) in variants.iter().enumerate() {
+        let line = line + y;
+        for (x, c) in row.chars().enumerate() {
+            let index = match variant_at(variants, x, y) {
+                None => continue,
+                Some(Ok(index)) => index,
+                Some(Err(c)) => {
+                    diagnostics.push(Diagnostic::error(
+                        line, x + 1, format!("invalid variant '{}'", c)));
+                    continue;
+                }
+            };
+
+            let def = match tiles.get(y).and_then(|r| r.chars().nth(x)) {
+                Some(tile) => match tileset.get(tile) {
+                    Some(def) => def,
+                    None => continue,
+                },
+                None => {
+                    diagnostics.push(Diagnostic::error(
+                        line, x + 1, format!("variant '{}' is outside of the tiles", c)));
+                    continue;
+                }
+            };
+
+            // the auto-tiled tiles have the variants of their neighbour mask
+            let sprites = tileset.sprites_at(tiles, x, y);
+            if sprites.len() < 2 {
+                diagnostics.push(Diagnostic::warning(
+                    line, x + 1, format!("tile '{}' has no variants", def.symbol)));
+            } else if index >= sprites.len() {
+                diagnostics.push(Diagnostic::error(
+                    line, x + 1, format!("tile '{}' has {} variants, found variant '{}'",
+                                         def.symbol, sprites.len(), c)));
+            }
+        }
+    }
+}
+
+// Tiled maps can't have ragged rows or unknown tiles, only their objects are
+// checked
+fn validate_tiled(path: &str) -> Vec<Diagnostic> {
+    let map = match TiledMap::load(path) {
+        Ok(map) => map,
+        Err(e) => return vec![Diagnostic::error(e.line, 0, e.message)],
+    };
+
+    let mut diagnostics = Vec::new();
+    if let Err(e) = map.meta() {
+        diagnostics.push(Diagnostic::error(0, 0, e.message));
+    }
+
+    for kind in &["start", "exit"] {
+        match map.objects.iter().filter(|o| o.kind == *kind).count() {
+            0 => diagnostics.push(Diagnostic::error(
+                0, 0, format!("missing {} object", kind))),
+            1 => {},
+            n => diagnostics.push(Diagnostic::error(
+                0, 0, format!("{} {} objects, expected one", n, kind))),
+        }
+    }
+
+    for object in map.objects.iter().filter(|o| o.kind == "platform") {
+        diagnostics.push(Diagnostic::warning(
+            0, 0, format!("the platform object '{}' is ignored, Tiled maps have no moving platforms",
+                          object.name)));
+    }
+
+    diagnostics
+}
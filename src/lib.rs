@@ -0,0 +1,17 @@
+// src/lib.rs
+
+extern crate glm;
+#[macro_use]
+extern crate log;
+extern crate rand;
+extern crate sdl2;
+extern crate serde_json;
+extern crate time;
+extern crate xml;
+
+pub mod config;
+pub mod crash;
+pub mod level;
+pub mod phi;
+pub mod replay;
+pub mod views;
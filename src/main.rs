@@ -1,15 +1,59 @@
 // src/main.rs
 
-extern crate glm;
-extern crate rand;
-extern crate sdl2;
-extern crate time;
+#[macro_use]
+extern crate log;
+extern crate platform_rs;
 
-mod phi;
-mod views;
+use platform_rs::config::{Config, USAGE};
+use platform_rs::replay::Replay;
+use platform_rs::views::editor::EditorView;
+use platform_rs::views::game::GameView;
+use platform_rs::views::menu::MenuView;
+use platform_rs::{crash, phi};
+
+use std::path::PathBuf;
+use std::rc::Rc;
 
 fn main() {
-    ::phi::spawn("Platform-RS platformer", |phi| {
-        Box::new(::views::menu::MenuView::new(phi))
+    let config = match Config::from_args(::std::env::args().skip(1)) {
+        Ok(config) => config,
+        Err(msg) => {
+            if msg.is_empty() {
+                println!("{}", USAGE);
+                return;
+            }
+            eprintln!("{}\n\n{}", msg, USAGE);
+            ::std::process::exit(1);
+        }
+    };
+
+    phi::logger::init(phi::logger::LoggerConfig {
+        level: config.log_level,
+        targets: config.log_targets.clone(),
+        file: config.log_file.as_ref().map(PathBuf::from),
+    }).unwrap();
+    debug!("{:?}", config);
+    crash::install(&config);
+
+    // load the replay before opening the window to report errors early
+    let replay = match config.replay {
+        Some(ref path) => match Replay::load(path) {
+            Ok(replay) => Some(replay),
+            Err(msg) => {
+                error!("cannot load the replay {}", msg);
+                ::std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+
+    let config = Rc::new(config);
+    phi::spawn("Platform-RS platformer", config.debug, |phi| {
+        match (replay.as_ref(), config.edit.as_ref()) {
+            (Some(replay), _) => Box::new(
+                GameView::replay(phi, config.clone(), replay.clone())),
+            (None, Some(path)) => Box::new(EditorView::new(phi, config.clone(), path)),
+            (None, None) => Box::new(MenuView::new(phi, config.clone())),
+        }
     });
 }
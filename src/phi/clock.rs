@@ -0,0 +1,169 @@
+// src/phi/clock.rs
+
+use phi::ImmediateEvents;
+
+/// Time scales selectable at runtime, `1.0` being real time.
+const SCALES: [f64; 6] = [0.1, 0.25, 0.5, 1.0, 2.0, 4.0];
+const REAL_TIME: usize = 3;
+
+/// Debug controls over the time passed to `View::update`, only enabled with
+/// the `--debug` option.
+///
+/// * F5 pauses and resumes the simulation, the view is still rendered
+/// * F6 advances a paused simulation by exactly one update
+/// * F7 and F8 slow down and speed up the time
+pub struct Clock {
+    debug: bool,
+    paused: bool,
+    step: bool,
+    scale: usize,
+}
+
+impl Clock {
+    /// The keys are ignored unless `debug` is set, the time then passes
+    /// unchanged.
+    pub fn new(debug: bool) -> Clock {
+        Clock {
+            debug: debug,
+            paused: false,
+            step: false,
+            scale: REAL_TIME,
+        }
+    }
+
+    /// React to the debug keys pressed in this frame, return `true` if the
+    /// state of the clock changed.
+    pub fn handle(&mut self, events: &ImmediateEvents) -> bool {
+        if !self.debug {
+            return false;
+        }
+
+        let mut changed = false;
+
+        if events.key_f5 == Some(true) {
+            self.paused = !self.paused;
+            info!("simulation {}", if self.paused { "paused" } else { "resumed" });
+            changed = true;
+        }
+
+        if events.key_f6 == Some(true) {
+            self.paused = true;
+            self.step = true;
+            changed = true;
+        }
+
+        if events.key_f7 == Some(true) && self.scale > 0 {
+            self.scale -= 1;
+            info!("time scale set to {}x", SCALES[self.scale]);
+            changed = true;
+        }
+
+        if events.key_f8 == Some(true) && self.scale < SCALES.len() - 1 {
+            self.scale += 1;
+            info!("time scale set to {}x", SCALES[self.scale]);
+            changed = true;
+        }
+
+        changed
+    }
+
+    /// Scaled time to pass to `View::update`, or `None` if the simulation
+    /// must not advance in this frame.
+    ///
+    /// A single step advances the simulation by `interval`, the duration of
+    /// a frame at the target frame rate, so that it doesn't depend on how
+    /// long the clock has been paused.
+    pub fn tick(&mut self, elapsed: f64, interval: f64) -> Option<f64> {
+        if !self.paused {
+            Some(elapsed * SCALES[self.scale])
+        } else if self.step {
+            self.step = false;
+            Some(interval * SCALES[self.scale])
+        } else {
+            None
+        }
+    }
+
+    /// Text describing the state of the clock, `None` when running in real
+    /// time.
+    pub fn status(&self) -> Option<String> {
+        match (self.paused, self.scale == REAL_TIME) {
+            (false, true) => None,
+            (false, false) => Some(format!("{}x", SCALES[self.scale])),
+            (true, true) => Some("PAUSED".to_string()),
+            (true, false) => Some(format!("PAUSED {}x", SCALES[self.scale])),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Clock;
+    use phi::ImmediateEvents;
+
+    const INTERVAL: f64 = 1.0 / 60.0;
+
+    // press the keys chosen by `keys` for one frame
+    fn press<F>(clock: &mut Clock, keys: F) -> bool where F: Fn(&mut ImmediateEvents) {
+        let mut events = ImmediateEvents::new();
+        keys(&mut events);
+        clock.handle(&events)
+    }
+
+    #[test]
+    fn a_paused_clock_passes_no_time() {
+        let mut clock = Clock::new(true);
+        assert!(press(&mut clock, |e| e.key_f5 = Some(true)));
+        assert_eq!(clock.tick(0.02, INTERVAL), None);
+        assert_eq!(clock.tick(0.5, INTERVAL), None);
+        assert_eq!(clock.status(), Some("PAUSED".to_string()));
+
+        press(&mut clock, |e| e.key_f5 = Some(true));
+        assert_eq!(clock.tick(0.02, INTERVAL), Some(0.02));
+    }
+
+    #[test]
+    fn a_step_passes_one_interval() {
+        let mut clock = Clock::new(true);
+        press(&mut clock, |e| e.key_f6 = Some(true));
+
+        // however long the frame took, then nothing until the next step
+        assert_eq!(clock.tick(0.5, INTERVAL), Some(INTERVAL));
+        assert_eq!(clock.tick(0.5, INTERVAL), None);
+
+        press(&mut clock, |e| e.key_f6 = Some(true));
+        assert_eq!(clock.tick(0.001, INTERVAL), Some(INTERVAL));
+    }
+
+    #[test]
+    fn the_time_scale_stays_between_a_tenth_and_four_times() {
+        let mut clock = Clock::new(true);
+        for _ in 0..10 {
+            press(&mut clock, |e| e.key_f7 = Some(true));
+        }
+        assert_eq!(clock.tick(1.0, INTERVAL), Some(0.1));
+        assert!(!press(&mut clock, |e| e.key_f7 = Some(true)));
+
+        for _ in 0..10 {
+            press(&mut clock, |e| e.key_f8 = Some(true));
+        }
+        assert_eq!(clock.tick(1.0, INTERVAL), Some(4.0));
+        assert!(!press(&mut clock, |e| e.key_f8 = Some(true)));
+        assert_eq!(clock.status(), Some("4x".to_string()));
+    }
+
+    #[test]
+    fn without_debug_the_time_passes_unchanged() {
+        let mut clock = Clock::new(false);
+        let changed = press(&mut clock, |e| {
+            e.key_f5 = Some(true);
+            e.key_f6 = Some(true);
+            e.key_f8 = Some(true);
+        });
+
+        assert!(!changed);
+        assert_eq!(clock.tick(0.02, INTERVAL), Some(0.02));
+        assert_eq!(clock.tick(0.125, INTERVAL), Some(0.125));
+        assert_eq!(clock.status(), None);
+    }
+}
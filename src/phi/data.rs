@@ -111,6 +111,24 @@ impl Rectangle {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Circle {
+    pub center: Vector2<f64>,
+    pub radius: f64,
+}
+
+impl Circle {
+    /// Whether the circle and the rectangle have a point in common.
+    pub fn intersects(&self, rect: &Rectangle) -> bool {
+        // the point of the rectangle closest to the center
+        let x = self.center.x.max(rect.x).min(rect.x + rect.w);
+        let y = self.center.y.max(rect.y).min(rect.y + rect.h);
+
+        let (dx, dy) = (self.center.x - x, self.center.y - y);
+        dx * dx + dy * dy <= self.radius * self.radius
+    }
+}
+
 pub struct MaybeAlive<T> {
     pub alive: bool,
     pub value: T,
@@ -10,7 +10,13 @@ macro_rules! struct_events {
         pub struct ImmediateEvents {
             resize: Option<(u32, u32)>,
             $( pub $k_alias: Option<bool>),*,
-            $( pub $e_alias: bool ),*
+            $( pub $e_alias: bool ),*,
+
+            pub mouse_left: Option<bool>,
+            pub mouse_right: Option<bool>,
+
+            /// text typed during the frame
+            pub text: String,
         }
 
         impl ImmediateEvents {
@@ -18,16 +24,58 @@ macro_rules! struct_events {
                 ImmediateEvents {
                     resize: None,
                     $( $k_alias: None ),*,
-                    $( $e_alias: false ),*
+                    $( $e_alias: false ),*,
+
+                    mouse_left: None,
+                    mouse_right: None,
+                    text: String::new(),
                 }
             }
         }
 
+        /// Keys held down during a frame, used to record and play back the
+        /// input of a session.
+        #[derive(Clone, Copy, Debug, Default, PartialEq)]
+        pub struct KeyState {
+            $( pub $k_alias: bool ),*
+        }
+
+        impl KeyState {
+            /// Names of the keys held down.
+            pub fn names(&self) -> Vec<&'static str> {
+                let mut names = Vec::new();
+                $(
+                    if self.$k_alias {
+                        names.push(stringify!($k_alias));
+                    }
+                )*
+                names
+            }
+
+            /// Build the state from the names returned by `names()`.
+            pub fn from_names<'a, I>(names: I) -> Result<KeyState, String>
+                where I: Iterator<Item=&'a str> {
+                let mut state = KeyState::default();
+                for name in names {
+                    match name {
+                        $( stringify!($k_alias) => state.$k_alias = true, )*
+                        _ => return Err(format!("Unknown key '{}'", name)),
+                    }
+                }
+                Ok(state)
+            }
+        }
+
         pub struct Events {
             pump: EventPump,
             pub now: ImmediateEvents,
 
             $( pub $k_alias: bool ),*,
+
+            /// position of the mouse pointer in the window
+            pub mouse: (i32, i32),
+            pub mouse_left: bool,
+            pub mouse_right: bool,
         }
 
         impl Events {
@@ -37,9 +85,32 @@ macro_rules! struct_events {
                     now: ImmediateEvents::new(),
 
                     $( $k_alias: false),*,
+
+                    mouse: (0, 0),
+                    mouse_left: false,
+                    mouse_right: false,
                 }
             }
 
+            pub fn key_state(&self) -> KeyState {
+                KeyState {
+                    $( $k_alias: self.$k_alias ),*
+                }
+            }
+
+            /// Replace the keyboard state with `keys`, the immediate events
+            /// are updated as if the user pressed or released the keys.
+            pub fn set_key_state(&mut self, keys: &KeyState) {
+                $(
+                    self.now.$k_alias = if keys.$k_alias != self.$k_alias {
+                        Some(keys.$k_alias)
+                    } else {
+                        None
+                    };
+                    self.$k_alias = keys.$k_alias;
+                )*
+            }
+
             pub fn pump(&mut self, renderer: &mut ::sdl2::render::Renderer) {
                 self.now = ImmediateEvents::new();
 
@@ -74,6 +145,44 @@ macro_rules! struct_events {
                             ),*,
                             _ => {}
                         },
+
+                        MouseMotion { x, y, .. } => {
+                            self.mouse = (x, y);
+                        },
+
+                        MouseButtonDown { mouse_btn, x, y, .. } => {
+                            self.mouse = (x, y);
+                            match mouse_btn {
+                                ::sdl2::mouse::MouseButton::Left => {
+                                    self.now.mouse_left = Some(true);
+                                    self.mouse_left = true;
+                                },
+                                ::sdl2::mouse::MouseButton::Right => {
+                                    self.now.mouse_right = Some(true);
+                                    self.mouse_right = true;
+                                },
+                                _ => {}
+                            }
+                        },
+
+                        MouseButtonUp { mouse_btn, x, y, .. } => {
+                            self.mouse = (x, y);
+                            match mouse_btn {
+                                ::sdl2::mouse::MouseButton::Left => {
+                                    self.now.mouse_left = Some(false);
+                                    self.mouse_left = false;
+                                },
+                                ::sdl2::mouse::MouseButton::Right => {
+                                    self.now.mouse_right = Some(false);
+                                    self.mouse_right = false;
+                                },
+                                _ => {}
+                            }
+                        },
+
+                        TextInput { text, .. } => {
+                            self.now.text.push_str(&text);
+                        },
                         $(
                             $e_sdl => {
                                 self.now.$e_alias = true;
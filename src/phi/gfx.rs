@@ -41,7 +41,16 @@ impl Sprite {
     }
 
     pub fn load(renderer: &Renderer, path: &str) -> Option<Sprite> {
-        renderer.load_texture(Path::new(path)).ok().map(Sprite::new)
+        match renderer.load_texture(Path::new(path)) {
+            Ok(texture) => {
+                debug!("loaded sprite {}", path);
+                Some(Sprite::new(texture))
+            },
+            Err(e) => {
+                warn!("cannot load sprite {}: {}", path, e);
+                None
+            }
+        }
     }
 
     pub fn region(&self, rect: Rectangle) -> Option<Sprite> {
@@ -64,6 +73,18 @@ impl Sprite {
     pub fn size(&self) -> (f64, f64) {
         (self.src.w, self.src.h)
     }
+
+    /// Opacity of the following renders, from 0 to 255. The sprites sharing
+    /// the texture, like the regions and the clones, are affected as well.
+    pub fn set_alpha(&self, alpha: u8) {
+        self.tex.borrow_mut().set_alpha_mod(alpha);
+    }
+
+    /// Tint of the following renders, multiplied with the colors of the
+    /// texture, which is shared like with `set_alpha`.
+    pub fn set_color(&self, red: u8, green: u8, blue: u8) {
+        self.tex.borrow_mut().set_color_mod(red, green, blue);
+    }
 }
 
 impl Renderable for Sprite {
@@ -146,6 +167,31 @@ impl AnimatedSprite {
             self.current_time -= self.max_time;
         }
     }
+
+    // tint every frame, see `Sprite::set_color`
+    pub fn set_color(&self, red: u8, green: u8, blue: u8) {
+        for sprite in self.sprites.iter() {
+            sprite.set_color(red, green, blue);
+        }
+    }
+
+    // go back to the first frame
+    pub fn reset(&mut self) {
+        self.current_time = 0.0;
+    }
+
+    // like `add_time`, but the animation stops on its last frame instead of
+    // starting over, returns whether the last frame has been shown for its
+    // whole delay
+    pub fn add_time_once(&mut self, dt: f64) -> bool {
+        self.current_time += dt;
+        if self.current_time >= self.max_time {
+            self.current_time = self.max_time - self.frame_delay / 2.0;
+            true
+        } else {
+            false
+        }
+    }
 }
 
 impl Renderable for AnimatedSprite {
@@ -192,6 +238,11 @@ impl SpriteBuilder {
         }
     }
 
+    /// Size of the whole spritesheet, in pixels.
+    pub fn sheet_size(&self) -> (f64, f64) {
+        self.spritesheet.size()
+    }
+
     pub fn region_from(&mut self, x: f64, y: f64) -> &mut SpriteBuilder {
         self.region.w += self.region.x - x;
         self.region.h += self.region.y - y;
@@ -248,7 +299,7 @@ impl SpriteBuilder {
     }
 }
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum TileCollision {
     /// a tile which doesn't hinder player motion at all
     Passable = 0,
@@ -281,6 +332,7 @@ impl Tile {
         let sprite = match Sprite::load(&mut phi.renderer, path) {
             Some(sprite) => { Some(sprite) },
             None => {
+                error!("tile sprite {} not found", path);
                 panic!("Sprite {} not found!", path);
             }
         };
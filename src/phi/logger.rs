@@ -0,0 +1,176 @@
+// src/phi/logger.rs
+
+use log::{self, Log, LogLevelFilter, LogMetadata, LogRecord, SetLoggerError};
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::io::prelude::*;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Size in bytes after which the log file is rotated.
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+
+/// Number of rotated files kept around, `<file>.1` being the most recent.
+const MAX_ROTATED_FILES: usize = 3;
+
+pub struct LoggerConfig {
+    /// level used for every target without an explicit override
+    pub level: LogLevelFilter,
+
+    /// per-target overrides, the targets are module paths relative to the
+    /// crate root such as `phi::gfx` or `views::game`
+    pub targets: Vec<(String, LogLevelFilter)>,
+
+    /// when set the log is also appended to this file
+    pub file: Option<PathBuf>,
+}
+
+struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFile {
+    fn open(path: PathBuf) -> io::Result<RotatingFile> {
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+
+        Ok(RotatingFile {
+            path: path,
+            file: file,
+            size: size,
+        })
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{}", index));
+        PathBuf::from(name)
+    }
+
+    // shift `<file>.N` to `<file>.N+1`, dropping the oldest one, and start
+    // again with an empty file
+    fn rotate(&mut self) -> io::Result<()> {
+        for index in (1..MAX_ROTATED_FILES).rev() {
+            let from = self.rotated_path(index);
+            if from.exists() {
+                fs::rename(&from, self.rotated_path(index + 1))?;
+            }
+        }
+        fs::rename(&self.path, self.rotated_path(1))?;
+
+        self.file = File::create(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        if self.size > 0 && self.size + line.len() as u64 > MAX_FILE_SIZE {
+            self.rotate()?;
+        }
+
+        self.file.write_all(line.as_bytes())?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+}
+
+struct Logger {
+    level: LogLevelFilter,
+    targets: Vec<(String, LogLevelFilter)>,
+    file: Option<Mutex<RotatingFile>>,
+}
+
+impl Logger {
+    // level for `target`, the most specific override wins
+    fn level_for(&self, target: &str) -> LogLevelFilter {
+        // strip the crate name so that `phi::gfx` matches `platform_rs::phi::gfx`
+        let crate_name = module_path!().split("::").next().unwrap();
+        let relative = if target.starts_with(&format!("{}::", crate_name)) {
+            &target[crate_name.len() + 2..]
+        } else {
+            target
+        };
+
+        let mut best: Option<&(String, LogLevelFilter)> = None;
+        for entry in &self.targets {
+            let module = &entry.0;
+            let matches = [target, relative].iter().any(|t| {
+                *t == module || t.starts_with(&format!("{}::", module))
+            });
+
+            if matches && best.map_or(true, |b| b.0.len() < module.len()) {
+                best = Some(entry);
+            }
+        }
+
+        best.map_or(self.level, |b| b.1)
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &LogMetadata) -> bool {
+        metadata.level() <= self.level_for(metadata.target())
+    }
+
+    fn log(&self, record: &LogRecord) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let now = ::time::now();
+        let line = format!("{}.{:03} {:<5} [{}] {}\n",
+                           now.strftime("%Y-%m-%d %H:%M:%S").unwrap(),
+                           now.tm_nsec / 1_000_000,
+                           record.level(),
+                           record.target(),
+                           record.args());
+
+        // logging must never bring the game down, errors are ignored
+        let _ = io::stderr().write_all(line.as_bytes());
+        if let Some(ref file) = self.file {
+            if let Ok(mut file) = file.lock() {
+                let _ = file.write_line(&line);
+            }
+        }
+    }
+}
+
+/// Install the global logger described by `config`.
+///
+/// Records go to stderr and, if `config.file` is set, to a file which is
+/// rotated once it grows over `MAX_FILE_SIZE` bytes. If the file can't be
+/// opened the logger falls back to stderr alone.
+pub fn init(config: LoggerConfig) -> Result<(), SetLoggerError> {
+    let LoggerConfig { level, targets, file } = config;
+
+    let mut file_error = None;
+    let file = file.and_then(|path| {
+        match RotatingFile::open(path.clone()) {
+            Ok(file) => Some(Mutex::new(file)),
+            Err(e) => {
+                file_error = Some(format!("cannot open log file {}: {}", path.display(), e));
+                None
+            }
+        }
+    });
+
+    let max_level = targets.iter()
+        .map(|t| t.1)
+        .fold(level, |a, b| if b > a { b } else { a });
+
+    log::set_logger(|max_log_level| {
+        max_log_level.set(max_level);
+        Box::new(Logger {
+            level: level,
+            targets: targets,
+            file: file,
+        })
+    })?;
+
+    if let Some(msg) = file_error {
+        warn!("{}", msg);
+    }
+    Ok(())
+}
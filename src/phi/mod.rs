@@ -1,8 +1,10 @@
 // phi/mod.rs
 #[macro_use]
 mod events;
+pub mod clock;
 pub mod data;
 pub mod gfx;
+pub mod logger;
 
 use sdl2::pixels;
 use sdl2::rect::Rect;
@@ -19,6 +21,16 @@ struct_events! {
         key_left: Left,
         key_right: Right,
         key_space: Space,
+        key_tab: Tab,
+        key_return: Return,
+        key_backspace: Backspace,
+
+        key_f2: F2,
+        key_f5: F5,
+        key_f6: F6,
+        key_f7: F7,
+        key_f8: F8,
+        key_f9: F9,
 
         key_1: Num1,
         key_2: Num2,
@@ -59,16 +71,25 @@ impl<'window, 'font> Phi<'window, 'font> {
                           size: u16, color: pixels::Color) -> Option<gfx::Sprite> {
         let couple = (font_path, size);
         if let Some(font) = self.cached_fonts.get(&couple) {
-            return font.render(text).blended(color).ok()
-                .and_then(|surface| self.renderer.create_texture_from_surface(&surface).ok())
+            return font.render(text).blended(color)
+                .map_err(|e| e.to_string())
+                .and_then(|surface| self.renderer.create_texture_from_surface(&surface)
+                          .map_err(|e| e.to_string()))
                 .map(gfx::Sprite::new)
+                .map_err(|e| warn!("cannot render text {:?}: {}", text, e))
+                .ok()
         }
 
-        if let Some(font) = self.font_ctx.load_font(path::Path::new(font_path), size).ok() {
-            self.cached_fonts.insert(couple, font);
-            self.ttf_str_sprite(text, font_path, size, color)
-        } else {
-            None
+        match self.font_ctx.load_font(path::Path::new(font_path), size) {
+            Ok(font) => {
+                debug!("loaded font {} at size {}", font_path, size);
+                self.cached_fonts.insert(couple, font);
+                self.ttf_str_sprite(text, font_path, size, color)
+            },
+            Err(e) => {
+                warn!("cannot load font {}: {}", font_path, e);
+                None
+            }
         }
     }
 }
@@ -94,7 +115,8 @@ pub trait View {
 }
 
 /// Create a window with name `title`, initialize the underlying libraries and
-/// start the game with the `View` returned by `init()`.
+/// start the game with the `View` returned by `init()`. `debug` enables the
+/// keys of `clock::Clock`.
 ///
 /// # Examples
 ///
@@ -117,37 +139,41 @@ pub trait View {
 ///     }
 /// }
 ///
-/// spawn("Example", |_| {
+/// spawn("Example", false, |_| {
 ///     Box::new(MyView)
 /// });
 /// ```
 
-pub fn spawn<F>(title: &str, init: F)
+pub fn spawn<F>(title: &str, debug: bool, init: F)
     where F: Fn(&mut Phi) -> Box<View> {
 
     // initialize SDL2
-    let sdl_context = ::sdl2::init().unwrap();
-    let video = sdl_context.video().unwrap();
+    info!("initializing SDL2");
+    let sdl_context = ::sdl2::init().unwrap_or_else(|e| fatal("SDL2", e));
+    let video = sdl_context.video().unwrap_or_else(|e| fatal("the video subsystem", e));
 
     // initialize the image support
-    let _image_context = ::sdl2::image::init(::sdl2::image::INIT_PNG).unwrap();
+    info!("initializing SDL2_image");
+    let _image_context = ::sdl2::image::init(::sdl2::image::INIT_PNG)
+        .unwrap_or_else(|e| fatal("SDL2_image", e));
 
     // and the font support
-    let font_ctx = ttf::init().unwrap();
+    info!("initializing SDL2_ttf");
+    let font_ctx = ttf::init().unwrap_or_else(|e| fatal("SDL2_ttf", e));
     let fps_font = font_ctx
         .load_font(path::Path::new("assets/fonts/liberation-mono.ttf"), 20)
-        .ok()
-        .unwrap();
+        .unwrap_or_else(|e| fatal("the FPS font", e));
 
     // create the window
+    info!("creating the window '{}' (800x600)", title);
     let window = video.window(title, 800, 600)
         .position_centered().opengl()
-        .build().unwrap();
+        .build().unwrap_or_else(|e| fatal("the window", e));
 
     // Create the context
     let mut context = Phi::new(
-        Events::new(sdl_context.event_pump().unwrap()),
-        window.renderer().accelerated().build().unwrap(),
+        Events::new(sdl_context.event_pump().unwrap_or_else(|e| fatal("the event pump", e))),
+        window.renderer().accelerated().build().unwrap_or_else(|e| fatal("the renderer", e)),
         &font_ctx
     );
 
@@ -161,6 +187,10 @@ pub fn spawn<F>(title: &str, init: F)
     let mut fps = 0u16;
 
     let mut fps_overlay: Option<Texture> = None;
+
+    // debug controls over the simulation time
+    let mut clock = clock::Clock::new(debug);
+    let mut clock_overlay: Option<Texture> = None;
     loop {
         // Frame timing
         let now = ::time::precise_time_ns() / 1000u64;
@@ -190,23 +220,82 @@ pub fn spawn<F>(title: &str, init: F)
         // logic and rendering
         context.events.pump(&mut context.renderer);
 
-        match current_view.update(&mut context, elapsed) {
-            ViewAction::Render(view) => {
-                current_view = view;
-                current_view.render(&mut context);
+        if clock.handle(&context.events.now) {
+            clock_overlay = clock.status().and_then(|status| {
+                fps_font.render(&status)
+                    .blended(pixels::Color::RGB(255, 0, 255)).ok()
+                    .and_then(|surface| context.renderer
+                              .create_texture_from_surface(&surface).ok())
+            });
+        }
+
+        current_view = match clock.tick(elapsed, interval as f64 / 1_000_000.0) {
+            Some(elapsed) => match current_view.update(&mut context, elapsed) {
+                ViewAction::Render(view) => view,
 
-                if let Some(ref texture) = fps_overlay {
-                    let TextureQuery{ width, height, ..} = texture.query();
-                    let dst = Some(Rect::new(10, 600 - height as i32 - 10,
-                                             width, height));
-                    context.renderer.copy(texture, None, dst);
-                }
+                ViewAction::Quit => {
+                    info!("quitting");
+                    break
+                },
+            },
 
-                context.renderer.present();
+            // the simulation is frozen but the window can still be closed
+            None if context.events.now.quit => {
+                info!("quitting");
+                break
             },
 
-            ViewAction::Quit =>
-                break,
+            None => current_view,
+        };
+
+        current_view.render(&mut context);
+
+        if let Some(ref texture) = fps_overlay {
+            let TextureQuery{ width, height, ..} = texture.query();
+            let dst = Some(Rect::new(10, 600 - height as i32 - 10,
+                                     width, height));
+            context.renderer.copy(texture, None, dst);
+        }
+
+        // the bottom right corner is the only one the views leave free
+        if let Some(ref texture) = clock_overlay {
+            let TextureQuery{ width, height, ..} = texture.query();
+            let dst = Some(Rect::new(800 - width as i32 - 10, 600 - height as i32 - 10,
+                                     width, height));
+            context.renderer.copy(texture, None, dst);
         }
+
+        context.renderer.present();
     }
 }
+
+/// Initialize the libraries like `spawn` but without showing anything and
+/// run `f`, for the tests which need a `Phi` to load the levels: SDL2 uses its
+/// dummy video driver and a software renderer.
+pub fn headless<F, R>(f: F) -> R
+    where F: FnOnce(&mut Phi) -> R {
+
+    ::sdl2::hint::set("SDL_VIDEODRIVER", "dummy");
+    let sdl_context = ::sdl2::init().unwrap_or_else(|e| fatal("SDL2", e));
+    let video = sdl_context.video().unwrap_or_else(|e| fatal("the video subsystem", e));
+    let _image_context = ::sdl2::image::init(::sdl2::image::INIT_PNG)
+        .unwrap_or_else(|e| fatal("SDL2_image", e));
+    let font_ctx = ttf::init().unwrap_or_else(|e| fatal("SDL2_ttf", e));
+
+    let window = video.window("headless", 800, 600)
+        .hidden()
+        .build().unwrap_or_else(|e| fatal("the window", e));
+    let mut context = Phi::new(
+        Events::new(sdl_context.event_pump().unwrap_or_else(|e| fatal("the event pump", e))),
+        window.renderer().software().build().unwrap_or_else(|e| fatal("the renderer", e)),
+        &font_ctx
+    );
+
+    f(&mut context)
+}
+
+// log the failed initialization of `what` and bail out
+fn fatal<E: ::std::fmt::Display, T>(what: &str, error: E) -> T {
+    error!("cannot initialize {}: {}", what, error);
+    panic!("cannot initialize {}: {}", what, error);
+}
@@ -0,0 +1,138 @@
+// src/replay.rs
+
+use level::campaign::START_LIVES;
+use phi::KeyState;
+
+use std::fs::File;
+use std::io::prelude::*;
+use std::io::{self, BufReader, BufWriter};
+
+const HEADER: &'static str = "# platform-rs replay";
+
+/// Input of a single frame.
+#[derive(Clone, Debug)]
+pub struct Frame {
+    /// time passed to `View::update`, in seconds
+    pub elapsed: f64,
+    pub keys: KeyState,
+}
+
+/// Everything needed to reproduce a game session: the RNG seeds, the level,
+/// the score and the lives at the start and the input of every frame.
+///
+/// The file is plain text: one `seed` line per level played, in order, a
+/// `level` line, `score` and `lives` lines, 0 and `START_LIVES` when missing,
+/// and one `frame` line per update with the elapsed time followed by the
+/// keys held down.
+/// The elapsed time is written with enough digits to read back the very
+/// same value, which keeps the playback deterministic.
+#[derive(Clone, Debug)]
+pub struct Replay {
+    /// seed of every level played, the first one is `level`
+    pub seeds: Vec<u32>,
+    pub level: String,
+    pub score: u32,
+    pub lives: u32,
+    pub frames: Vec<Frame>,
+}
+
+impl Replay {
+    pub fn new(level: &str, score: u32, lives: u32) -> Replay {
+        Replay {
+            seeds: Vec::new(),
+            level: level.to_string(),
+            score: score,
+            lives: lives,
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn load(path: &str) -> Result<Replay, String> {
+        let f = File::open(path).map_err(|e| format!("{}: {}", path, e))?;
+        let file = BufReader::new(&f);
+
+        let mut seeds = Vec::new();
+        let mut level = None;
+        let mut score = 0;
+        let mut lives = START_LIVES;
+        let mut frames = Vec::new();
+        for (lineno, line) in file.lines().enumerate() {
+            let line = line.map_err(|e| format!("{}: {}", path, e))?;
+            let mut words = line.split_whitespace();
+            let error = |msg: &str| format!("{}:{}: {}", path, lineno + 1, msg);
+
+            match words.next() {
+                None => {},
+                Some(word) if word.starts_with('#') => {},
+                Some("seed") => {
+                    seeds.push(words.next()
+                               .and_then(|s| s.parse::<u32>().ok())
+                               .ok_or_else(|| error("invalid seed"))?);
+                },
+                Some("level") => {
+                    level = Some(words.next()
+                                 .ok_or_else(|| error("missing level path"))?
+                                 .to_string());
+                },
+                Some("score") => {
+                    score = words.next()
+                        .and_then(|s| s.parse::<u32>().ok())
+                        .ok_or_else(|| error("invalid score"))?;
+                },
+                Some("lives") => {
+                    lives = words.next()
+                        .and_then(|s| s.parse::<u32>().ok())
+                        .filter(|&n| n > 0)
+                        .ok_or_else(|| error("invalid lives"))?;
+                },
+                Some("frame") => {
+                    let elapsed = words.next()
+                        .and_then(|s| s.parse::<f64>().ok())
+                        .ok_or_else(|| error("invalid elapsed time"))?;
+                    let keys = KeyState::from_names(words).map_err(|e| error(&e))?;
+
+                    frames.push(Frame {
+                        elapsed: elapsed,
+                        keys: keys,
+                    });
+                },
+                Some(word) => {
+                    return Err(error(&format!("unknown entry '{}'", word)));
+                }
+            }
+        }
+
+        if seeds.is_empty() {
+            return Err(format!("{}: missing seed", path));
+        }
+
+        Ok(Replay {
+            seeds: seeds,
+            level: level.ok_or_else(|| format!("{}: missing level", path))?,
+            score: score,
+            lives: lives,
+            frames: frames,
+        })
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+
+        writeln!(file, "{}", HEADER)?;
+        for seed in &self.seeds {
+            writeln!(file, "seed {}", seed)?;
+        }
+        writeln!(file, "level {}", self.level)?;
+        writeln!(file, "score {}", self.score)?;
+        writeln!(file, "lives {}", self.lives)?;
+        for frame in &self.frames {
+            write!(file, "frame {:?}", frame.elapsed)?;
+            for name in frame.keys.names() {
+                write!(file, " {}", name)?;
+            }
+            writeln!(file)?;
+        }
+
+        file.flush()
+    }
+}
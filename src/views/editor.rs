@@ -0,0 +1,532 @@
+// src/views/editor.rs
+
+use config::Config;
+use level::file::LevelFile;
+use level::tiled::TiledMap;
+use level::tileset::{Tileset, DEFAULT_TILESET};
+use level::validate::{validate, Severity};
+use phi::data::Rectangle;
+use phi::gfx::{CopySprite, RenderFx, Sprite};
+use phi::{Phi, View, ViewAction};
+use sdl2::pixels::Color;
+use std::collections::HashMap;
+use std::path::Path;
+use std::rc::Rc;
+
+// constants
+const FONT_NAME: &'static str = "assets/fonts/liberation-mono.ttf";
+const STATUS_H: f64 = 30.0;
+
+/// Level opened by the editor when none is given on the command line.
+pub const DEFAULT_LEVEL: &'static str = "assets/level-0.txt";
+
+// size of the levels created from scratch, as large as the window
+const NEW_WIDTH: usize = 20;
+const NEW_HEIGHT: usize = 15;
+
+/// Editor of the text levels.
+///
+/// * the arrows move the cursor, space paints the brush under it and
+///   backspace erases it
+/// * the left mouse button paints the brush, the right one erases
+/// * typing a tile character picks it as the brush, tab picks the next tile
+///   of the tileset
+/// * F2 saves the level, return play-tests it and escape goes back to the
+///   menu, after a second press when the level has unsaved changes
+///
+/// The tiles flagged as start or exit are unique: painting one removes the
+/// previous one.
+pub struct EditorView {
+    config: Rc<Config>,
+    path: String,
+    file: LevelFile,
+    tileset: Tileset,
+
+    // images of the tiles of the tileset by path, the tiles without one show
+    // their character instead
+    images: HashMap<String, Sprite>,
+    labels: Vec<Option<Sprite>>,
+
+    cursor: (usize, usize),
+
+    // index in the tileset of the tile painted by the brush and of the one
+    // painted when erasing
+    brush: usize,
+    blank: usize,
+
+    // top left corner of the view, in pixels
+    camera: (f64, f64),
+    tile_size: (f64, f64),
+
+    modified: bool,
+
+    // escape was pressed once with unsaved changes
+    leaving: bool,
+    message: String,
+    status_text: String,
+    status: Option<Sprite>,
+}
+
+impl EditorView {
+    /// Open the level at `path`, a new level is created when the file
+    /// doesn't exist.
+    ///
+    /// A Tiled map is converted to a text level with the default tileset,
+    /// which is saved next to it with the `.txt` extension: the map itself
+    /// is never written.
+    pub fn new(phi: &mut Phi, config: Rc<Config>, path: &str) -> EditorView {
+        info!("editing level {}", path);
+        let tiled = path.ends_with(".json") || path.ends_with(".tmx");
+        let map_path = path;
+        let text_path = if tiled {
+            Path::new(path).with_extension("txt").to_string_lossy().into_owned()
+        } else {
+            path.to_string()
+        };
+        let path = text_path.as_str();
+
+        let (file, message) = if tiled {
+            let converted = TiledMap::load(map_path).and_then(|map| {
+                Tileset::load(DEFAULT_TILESET).and_then(|tileset| map.to_text(&tileset))
+            });
+            match converted {
+                Ok(file) => {
+                    info!("{} converted to a text level, saved as {}", map_path, path);
+                    (file, format!("converted from the Tiled map, F2 saves it to {}", path))
+                },
+                Err(e) => {
+                    error!("{}: {}", map_path, e);
+                    (EditorView::empty_file(),
+                     format!("cannot convert the Tiled map: {}", e))
+                }
+            }
+        } else if Path::new(path).exists() {
+            match LevelFile::load(path) {
+                Ok(file) => (file, String::new()),
+                Err(e) => {
+                    error!("{}: {}", path, e);
+                    (EditorView::empty_file(), format!("cannot load the level: {}", e))
+                }
+            }
+        } else {
+            info!("{} doesn't exist, creating a new level", path);
+            (EditorView::empty_file(), "new level".to_string())
+        };
+
+        ::crash::set_view("editor");
+        ::crash::set_level(Some(path));
+
+        // a level with broken metadata is edited with the default tileset
+        let tileset_path = file.meta().ok()
+            .and_then(|meta| meta.tileset)
+            .unwrap_or_else(|| DEFAULT_TILESET.to_string());
+        let tileset = Tileset::load(&tileset_path).unwrap_or_else(|e| {
+            error!("{}", e);
+            panic!("Cannot load the tileset {}: {}", tileset_path, e)
+        });
+
+        // the brush and the eraser are tiles of the tileset
+        if tileset.tiles.is_empty() {
+            error!("{}: the tileset has no tiles", tileset_path);
+            panic!("Cannot edit levels with the empty tileset {}", tileset_path);
+        }
+
+        let mut images: HashMap<String, Sprite> = HashMap::new();
+        let mut labels = Vec::with_capacity(tileset.tiles.len());
+        for tile in &tileset.tiles {
+            let paths = tile.sprites.iter()
+                .chain(tile.masks.iter().flat_map(|&(_, ref images)| images));
+            for path in paths {
+                if !images.contains_key(path) {
+                    let sprite = Sprite::load(&phi.renderer, path).unwrap_or_else(|| {
+                        panic!("Tile {} not found!", path)
+                    });
+                    images.insert(path.clone(), sprite);
+                }
+            }
+
+            labels.push(if tile.sprites.is_empty() && tile.masks.is_empty() {
+                phi.ttf_str_sprite(&tile.symbol.to_string(), FONT_NAME, 20,
+                                   Color::RGB(220, 220, 220))
+            } else {
+                None
+            });
+        }
+
+        // erasing paints the first tile which is nothing at all
+        let blank = tileset.tiles.iter().position(|t| {
+            t.sprites.is_empty() && t.flags == Default::default() &&
+                t.collision == ::phi::gfx::TileCollision::Passable
+        }).unwrap_or(0);
+
+        let tile_size = file.meta()
+            .map(|meta| (meta.tile_width, meta.tile_height))
+            .unwrap_or((::level::file::DEFAULT_TILE_WIDTH, ::level::file::DEFAULT_TILE_HEIGHT));
+
+        EditorView {
+            config: config,
+            path: path.to_string(),
+            file: file,
+            tileset: tileset,
+            images: images,
+            labels: labels,
+            cursor: (0, 0),
+            brush: blank,
+            blank: blank,
+            camera: (0.0, 0.0),
+            tile_size: tile_size,
+            // the converted map is not on disk yet
+            modified: tiled,
+            leaving: false,
+            message: message,
+            status_text: String::new(),
+            status: None,
+        }
+    }
+
+    // a level with only a floor, the start on the left and the exit on the
+    // right
+    fn empty_file() -> LevelFile {
+        let mut tiles = vec![".".repeat(NEW_WIDTH); NEW_HEIGHT - 2];
+        tiles.push(format!(".1{}X.", ".".repeat(NEW_WIDTH - 4)));
+        tiles.push("#".repeat(NEW_WIDTH));
+
+        LevelFile {
+            properties: Vec::new(),
+            tiles: tiles,
+            tiles_line: 1,
+            variants: Vec::new(),
+            variants_line: 0,
+            layers: Vec::new(),
+            platforms: Vec::new(),
+            comments: Vec::new(),
+            crlf: false,
+        }
+    }
+
+    fn size(&self) -> (usize, usize) {
+        let width = self.file.tiles.iter().map(|row| row.chars().count()).max().unwrap_or(0);
+        (width, self.file.tiles.len())
+    }
+
+    // put the tile `index` of the tileset at `x`, `y`
+    fn paint(&mut self, x: usize, y: usize, index: usize) {
+        let symbol = self.tileset.tiles[index].symbol;
+        let flags = self.tileset.tiles[index].flags;
+        let blank = self.tileset.tiles[self.blank].symbol;
+
+        if self.file.tiles.get(y).and_then(|row| row.chars().nth(x)) == Some(symbol) {
+            return;
+        }
+
+        // there can be only one start and one exit
+        if flags.start || flags.exit {
+            let tileset = &self.tileset;
+            for row in &mut self.file.tiles {
+                *row = row.chars().map(|c| match tileset.get(c) {
+                    Some(t) if (t.flags.start && flags.start) || (t.flags.exit && flags.exit) => blank,
+                    _ => c,
+                }).collect();
+            }
+        }
+
+        while self.file.tiles.len() <= y {
+            self.file.tiles.push(String::new());
+        }
+        let mut row: Vec<char> = self.file.tiles[y].chars().collect();
+        while row.len() <= x {
+            row.push(blank);
+        }
+        row[x] = symbol;
+        self.file.tiles[y] = row.into_iter().collect();
+
+        // the new tile draws a random variant
+        if let Some(row) = self.file.variants.get_mut(y) {
+            let mut chars: Vec<char> = row.chars().collect();
+            if x < chars.len() {
+                chars[x] = '.';
+                *row = chars.into_iter().collect();
+            }
+        }
+
+        self.modified = true;
+    }
+
+    fn save(&mut self) {
+        match self.file.save(&self.path) {
+            Ok(()) => {
+                info!("saved the level to {}", self.path);
+                self.modified = false;
+                self.message = "saved".to_string();
+            },
+            Err(e) => {
+                error!("cannot save the level to {}: {}", self.path, e);
+                self.message = format!("cannot save: {}", e);
+            }
+        }
+    }
+
+    // tile under the mouse pointer, if any
+    fn mouse_tile(&self, phi: &Phi) -> Option<(usize, usize)> {
+        let (mx, my) = phi.events.mouse;
+        if (my as f64) < STATUS_H {
+            return None;
+        }
+
+        let x = (mx as f64 + self.camera.0) / self.tile_size.0;
+        let y = (my as f64 - STATUS_H + self.camera.1) / self.tile_size.1;
+        let (width, height) = self.size();
+        if x < 0.0 || y < 0.0 || x as usize >= width || y as usize >= height {
+            None
+        } else {
+            Some((x as usize, y as usize))
+        }
+    }
+
+    // scroll the view to keep the cursor visible
+    fn follow_cursor(&mut self, phi: &Phi) {
+        let (win_w, win_h) = phi.output_size();
+        let (tw, th) = self.tile_size;
+        let x = self.cursor.0 as f64 * tw;
+        let y = self.cursor.1 as f64 * th;
+
+        if x < self.camera.0 {
+            self.camera.0 = x;
+        } else if x + tw > self.camera.0 + win_w {
+            self.camera.0 = x + tw - win_w;
+        }
+
+        if y < self.camera.1 {
+            self.camera.1 = y;
+        } else if y + th > self.camera.1 + win_h - STATUS_H {
+            self.camera.1 = y + th - win_h + STATUS_H;
+        }
+    }
+
+    // render the status bar again when its text changes
+    fn refresh_status(&mut self, phi: &mut Phi) {
+        let text = format!("{}{} | {},{} | brush '{}' | {}",
+                           self.path, if self.modified { "*" } else { "" },
+                           self.cursor.0 + 1, self.cursor.1 + 1,
+                           self.tileset.tiles[self.brush].symbol, self.message);
+        if text != self.status_text {
+            self.status = phi.ttf_str_sprite(&text, FONT_NAME, 16, Color::RGB(255, 255, 255));
+            self.status_text = text;
+        }
+    }
+}
+
+impl View for EditorView {
+    fn update(mut self: Box<Self>, phi: &mut Phi, _: f64) -> ViewAction {
+        if phi.events.now.quit {
+            return ViewAction::Quit
+        }
+
+        if phi.events.now.key_escape == Some(true) {
+            if self.modified && !self.leaving {
+                self.leaving = true;
+                self.message = "unsaved changes, escape again to lose them, F2 to save".to_string();
+                self.refresh_status(phi);
+                return ViewAction::Render(self)
+            }
+
+            if self.modified {
+                warn!("leaving the editor, the changes to {} are lost", self.path);
+            }
+            return ViewAction::Render(Box::new(
+                ::views::menu::MenuView::new(phi, self.config.clone())))
+        }
+
+        let before = (self.cursor, self.brush, self.modified);
+        let (width, height) = self.size();
+
+        // move the cursor
+        if phi.events.now.key_left == Some(true) && self.cursor.0 > 0 {
+            self.cursor.0 -= 1;
+        }
+        if phi.events.now.key_right == Some(true) && self.cursor.0 + 1 < width {
+            self.cursor.0 += 1;
+        }
+        if phi.events.now.key_up == Some(true) && self.cursor.1 > 0 {
+            self.cursor.1 -= 1;
+        }
+        if phi.events.now.key_down == Some(true) && self.cursor.1 + 1 < height {
+            self.cursor.1 += 1;
+        }
+
+        // pick the brush
+        let typed: Vec<char> = phi.events.now.text.chars().collect();
+        for c in typed {
+            if let Some(index) = self.tileset.tiles.iter().position(|t| t.symbol == c) {
+                self.brush = index;
+            }
+        }
+        if phi.events.now.key_tab == Some(true) {
+            self.brush = (self.brush + 1) % self.tileset.tiles.len();
+        }
+
+        // paint
+        let (x, y) = self.cursor;
+        if phi.events.now.key_space == Some(true) {
+            let brush = self.brush;
+            self.paint(x, y, brush);
+        }
+        if phi.events.now.key_backspace == Some(true) {
+            let blank = self.blank;
+            self.paint(x, y, blank);
+        }
+
+        if phi.events.mouse_left || phi.events.mouse_right {
+            if let Some((x, y)) = self.mouse_tile(phi) {
+                let index = if phi.events.mouse_left { self.brush } else { self.blank };
+                self.cursor = (x, y);
+                self.paint(x, y, index);
+            }
+        }
+
+        if (self.cursor, self.brush, self.modified) != before {
+            self.message.clear();
+            self.leaving = false;
+        }
+
+        if phi.events.now.key_f2 == Some(true) {
+            self.save();
+        }
+
+        // play-test the level as it is, unless the game can't load it
+        if phi.events.now.key_return == Some(true) {
+            let errors: Vec<_> = validate(&self.file, &self.tileset).into_iter()
+                .filter(|d| d.severity == Severity::Error)
+                .collect();
+
+            if errors.is_empty() {
+                info!("play-testing {}", self.path);
+                let config = self.config.clone();
+                let path = self.path.clone();
+                let file = self.file.clone();
+                return ViewAction::Render(Box::new(
+                    ::views::game::GameView::playtest(phi, config, &path, file, self)))
+            }
+
+            for e in &errors {
+                error!("{}:{}", self.path, e);
+            }
+            self.message = format!("{} error(s), the first one: {}", errors.len(), errors[0]);
+        }
+
+        self.follow_cursor(phi);
+        self.refresh_status(phi);
+
+        ViewAction::Render(self)
+    }
+
+    fn render(&self, phi: &mut Phi) {
+        phi.renderer.set_draw_color(Color::RGB(0, 0, 50));
+        phi.renderer.clear();
+
+        let (win_w, _) = phi.output_size();
+        let (tw, th) = self.tile_size;
+
+        for (y, row) in self.file.tiles.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                let rect = Rectangle {
+                    x: x as f64 * tw - self.camera.0,
+                    y: y as f64 * th - self.camera.1 + STATUS_H,
+                    w: tw,
+                    h: th,
+                };
+
+                let index = match self.tileset.tiles.iter().position(|t| t.symbol == c) {
+                    Some(index) => index,
+                    None => {
+                        // characters missing from the tileset
+                        phi.renderer.set_draw_color(Color::RGB(200, 0, 0));
+                        phi.renderer.fill_rect(rect.to_sdl()).unwrap();
+                        continue;
+                    }
+                };
+
+                // the auto-tiled tiles change with their neighbours
+                let sprites = self.tileset.sprites_at(&self.file.tiles, x, y);
+                if !sprites.is_empty() {
+                    let variant = match self.file.variant(x, y) {
+                        Some(Ok(v)) if v < sprites.len() => v,
+                        _ => 0,
+                    };
+                    phi.renderer.copy_sprite(&self.images[&sprites[variant]], &rect.to_sdl(),
+                                             RenderFx::None);
+                } else if index != self.blank {
+                    if let Some(ref label) = self.labels[index] {
+                        let (w, h) = label.size();
+                        let dest = Rectangle {
+                            x: rect.x + (tw - w) / 2.0,
+                            y: rect.y + (th - h) / 2.0,
+                            w: w,
+                            h: h,
+                        };
+                        phi.renderer.copy_sprite(label, &dest.to_sdl(), RenderFx::None);
+                    }
+                }
+
+                phi.renderer.set_draw_color(Color::RGB(40, 40, 90));
+                phi.renderer.draw_rect(rect.to_sdl()).unwrap();
+            }
+        }
+
+        // the cursor
+        phi.renderer.set_draw_color(Color::RGB(255, 255, 0));
+        phi.renderer.draw_rect(Rectangle {
+            x: self.cursor.0 as f64 * tw - self.camera.0,
+            y: self.cursor.1 as f64 * th - self.camera.1 + STATUS_H,
+            w: tw,
+            h: th,
+        }.to_sdl()).unwrap();
+
+        // the status bar, with the brush on the right
+        phi.renderer.set_draw_color(Color::RGB(70, 15, 70));
+        phi.renderer.fill_rect(Rectangle {
+            x: 0.0, y: 0.0, w: win_w, h: STATUS_H,
+        }.to_sdl()).unwrap();
+
+        if let Some(ref status) = self.status {
+            let (w, h) = status.size();
+            let dest = Rectangle { x: 10.0, y: (STATUS_H - h) / 2.0, w: w, h: h };
+            phi.renderer.copy_sprite(status, &dest.to_sdl(), RenderFx::None);
+        }
+
+        let brush = Rectangle {
+            x: win_w - STATUS_H - 5.0,
+            y: 2.0,
+            w: STATUS_H - 4.0,
+            h: STATUS_H - 4.0,
+        };
+        let tile = &self.tileset.tiles[self.brush];
+        let image = tile.sprites.first()
+            .or_else(|| tile.masks.first().and_then(|&(_, ref images)| images.first()));
+        if let Some(sprite) = image.map(|path| &self.images[path]) {
+            phi.renderer.copy_sprite(sprite, &brush.to_sdl(), RenderFx::None);
+        } else if let Some(ref label) = self.labels[self.brush] {
+            phi.renderer.copy_sprite(label, &brush.to_sdl(), RenderFx::None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EditorView;
+    use config::Config;
+    use phi;
+
+    use std::rc::Rc;
+
+    // the Tiled maps are edited as text levels saved beside them
+    #[test]
+    fn tiled_maps_are_converted_and_saved_as_text() {
+        phi::headless(|phi| {
+            let editor = EditorView::new(phi, Rc::new(Config::new()), "tests/maps/small.json");
+            assert_eq!(editor.path, "tests/maps/small.txt");
+            assert_eq!(editor.file.tiles, vec!["...G..", ".1--BX", "######"]);
+            assert!(editor.modified);
+        });
+    }
+}
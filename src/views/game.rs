@@ -2,145 +2,672 @@
 
 use glm;
 
+use config::Config;
+use level::{collision_rank, parse_collision};
+use level::campaign::{Campaign, Session};
+use level::physics::*;
+use level::file::{variant_char, DecorationLayer, LevelFile, LevelMeta, PlatformDef};
+use level::tiled::{LayerDepth, TiledMap};
+use level::tileset::{Tileset, DEFAULT_TILESET};
+use level::validate::{validate, Severity};
 use phi::{Phi, View, ViewAction};
-use phi::data::Rectangle;
+use phi::data::{Circle, Rectangle};
 use phi::gfx::*;
 
+use rand::{Rng, SeedableRng, StdRng};
+
+use replay::{Frame, Replay};
+
+use views::editor::EditorView;
+use views::gameover::GameOverView;
+use views::hud::{Hud, HudInfo};
+use views::victory::VictoryView;
+
 use sdl2::pixels;
 
 use std::cell::RefCell;
-use std::io::prelude::*;
-use std::io::BufReader;
-use std::fs::File;
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+use std::rc::Rc;
 
 // constants
 const DEBUG: bool = true;
 
-const TILE_WIDTH: f64 = 40.0;
-const TILE_HEIGHT: f64 = 32.0;
+// the seconds left when the exit is reached are worth points
+const POINTS_PER_SECOND: u32 = 5;
 
 struct GameLevel {
+    pub meta: LevelMeta,
     pub player: RefCell<Player>,
     pub layers: Vec<Sprite>,
+
+    // decoration drawn behind the tiles and over the player, the bottom
+    // layer first
+    pub background: Vec<Vec<Vec<Tile>>>,
     pub tiles: Vec<Vec<Tile>>,
+    pub foreground: Vec<Vec<Vec<Tile>>>,
     pub gems: Vec<Box<Gem>>,
-    pub start: glm::Vector2<f64>,
+    pub total_gems: usize,
+    pub enemies: Vec<Enemy>,
+    pub checkpoints: Vec<Checkpoint>,
+    pub platforms: Vec<MovingPlatform>,
+
+    // where the player respawns, the start or the last checkpoint reached,
+    // and the gems, the enemies, the score and the time the player gets back
+    // there
+    respawn_point: glm::Vector2<f64>,
+    spawn_gems: Vec<Box<Gem>>,
+    spawn_enemies: Vec<Enemy>,
+    spawn_score: u32,
+    spawn_time: f64,
+
+    // the gems being collected, which are not in `gems` anymore
+    pub collected: Vec<Box<CollectedGem>>,
     pub exit: glm::Vector2<f64>,
+    pub reached_exit: bool,
+
+    // the player has celebrated reaching the exit
+    pub complete: bool,
+
+    // the die animation of the player is over, play stops until `respawn`
+    pub dead: bool,
+
+    // seconds left to complete the level, play stops at 0
+    pub time_left: f64,
+
+    // points earned in the level, added to the score of the session when
+    // the level is complete
+    pub score: u32,
     pub width: usize,
     pub height: usize,
+
+    // every random choice made while building the level comes from this
+    // seed, so that the same seed always builds the same level
+    pub seed: u32,
+    seed_sprite: Option<Sprite>,
+
+    // the text level as loaded, with the variants drawn by the random
+    // generator when it has a `[variants]` section, or the Tiled map in the
+    // text format, `None` when it can't be written in it
+    source: Option<LevelFile>,
+}
+
+// what the loaders extract from the level files
+struct LevelParts {
+    meta: LevelMeta,
+    background: Vec<Vec<Vec<Tile>>>,
+    tiles: Vec<Vec<Tile>>,
+    foreground: Vec<Vec<Vec<Tile>>>,
+    gems: Vec<glm::Vector2<f64>>,
+
+    // the kind of the enemies and where their feet are
+    enemies: Vec<(char, glm::Vector2<f64>)>,
+
+    // the tiles of the checkpoints
+    checkpoints: Vec<(usize, usize)>,
+    platforms: Vec<MovingPlatform>,
+    start: glm::Vector2<f64>,
+    exit: glm::Vector2<f64>,
+    source: Option<LevelFile>,
+}
+
+// push `bounds` out of `obstacle`, a tile or a moving platform whose top was
+// at `previous_top` on the previous frame, `on_ground` is set when `bounds`
+// lands on it
+fn push_out(bounds: &mut Rectangle, on_ground: &mut bool, obstacle: &Rectangle,
+            previous_top: f64, collision: TileCollision, previous_bottom: f32) {
+    if collision == TileCollision::Passable {
+        return;
+    }
+
+    if let Some(depth) = bounds.intersection_depth(obstacle) {
+        if depth.y.abs() < depth.x.abs() || collision == TileCollision::Platform {
+            if previous_bottom <= previous_top as f32 {
+                *on_ground = true;
+            }
+
+            if collision == TileCollision::Impassable || *on_ground {
+                bounds.y += depth.y;
+            }
+        } else if collision == TileCollision::Impassable {
+            bounds.x += depth.x;
+        }
+    }
 }
 
 impl GameLevel {
-    pub fn load(phi: &mut Phi, path: &str) -> GameLevel {
-        let f = File::open(path).unwrap();
-        let file = BufReader::new(&f);
+    /// Load the level at `path`, `seed` overrides the one of the level.
+    ///
+    /// Maps exported by the Tiled editor are recognized by their `.json` or
+    /// `.tmx` extension, every other file is read as a text level.
+    pub fn load(phi: &mut Phi, path: &str, seed: Option<u32>) -> GameLevel {
+        info!("loading level {}", path);
+        ::crash::set_level(Some(path));
+
+        if path.ends_with(".json") || path.ends_with(".tmx") {
+            GameLevel::load_tiled(phi, path, seed)
+        } else {
+            GameLevel::load_text(phi, path, seed)
+        }
+    }
 
-        let mut gems: Vec<Box<Gem>> = Vec::new();
-        let gem_sprite = Sprite::load(&phi.renderer, "assets/sprites/gem.png").unwrap();
+    fn load_text(phi: &mut Phi, path: &str, seed: Option<u32>) -> GameLevel {
+        match LevelFile::load(path) {
+            Ok(file) => GameLevel::from_file(phi, path, file, seed),
+            Err(e) => {
+                error!("{}: {}", path, e);
+                panic!("Cannot load level {}: {}", path, e);
+            }
+        }
+    }
 
-        let mut lines: Vec<String> = Vec::new();
-        let mut width: usize = 0;
+    /// Build the text level `file`, `path` is only used in the messages.
+    fn from_file(phi: &mut Phi, path: &str, file: LevelFile, seed: Option<u32>) -> GameLevel {
+        let meta = match file.meta() {
+            Ok(meta) => meta,
+            Err(e) => {
+                error!("{}: {}", path, e);
+                panic!("Cannot load level {}: {}", path, e);
+            }
+        };
 
-        for line in file.lines() {
-            let buffer = line.unwrap().to_string();
-            width = buffer.len();
-            lines.push(buffer);
+        let tileset = match Tileset::for_meta(&meta) {
+            Ok(tileset) => tileset,
+            Err(e) => {
+                error!("{}: {}", path, e);
+                panic!("Cannot load the tileset of level {}: {}", path, e);
+            }
+        };
+
+        // a ragged grid or a missing start or exit can't be played
+        let diagnostics = validate(&file, &tileset);
+        for diagnostic in &diagnostics {
+            match diagnostic.severity {
+                Severity::Error => error!("{}:{}", path, diagnostic),
+                Severity::Warning => warn!("{}:{}", path, diagnostic),
+            }
+        }
+        if let Some(e) = diagnostics.iter().find(|d| d.severity == Severity::Error) {
+            panic!("Cannot load level {}: {}", path, e);
         }
 
+        let (seed, mut rng) = GameLevel::seed_rng(seed, &meta);
+        let mut gems: Vec<glm::Vector2<f64>> = Vec::new();
+        let mut enemies = Vec::new();
+        let mut checkpoints = Vec::new();
+
+        let lines = &file.tiles;
+        let width: usize = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
         let height: usize = lines.len();
+        debug!("level {} is {}x{} tiles", path, width, height);
         let mut yvec: Vec<Vec<Tile>> = Vec::with_capacity(height);
         let mut exit: glm::Vector2<f64> = glm::Vector2::new(0.0, 0.0);
         let mut start: glm::Vector2<f64> = glm::Vector2::new(0.0, 0.0);
+        let mut variants: Vec<String> = Vec::with_capacity(height);
         for yth in 0..height {
             let mut xvec: Vec<Tile> = Vec::with_capacity(width);
+            let mut row_variants = String::with_capacity(width);
             for (xth, tile_type) in lines[yth].chars().enumerate() {
-                xvec.push(match tile_type {
-                    '.' => {
-                        // Blank space
-                        Tile::new(None, TileCollision::Passable)
-                    },
-                    'X' => {
-                        // Exit point
-                        exit.x = xth as f64 * TILE_WIDTH + TILE_WIDTH / 2.0;
-                        exit.y = yth as f64 * TILE_HEIGHT + TILE_HEIGHT / 2.0;
-                        Tile::load(phi, "assets/tiles/exit.png", TileCollision::Passable)
-                    },
-                    'G' => {
-                        // Gem
-                        let pos = glm::Vector2::new(
-                            xth as f64 * TILE_WIDTH + TILE_WIDTH / 2.0,
-                            yth as f64 * TILE_HEIGHT + TILE_HEIGHT / 2.0,
-                        );
-
-                        // put the gem into the gem list
-                        gems.push(Box::new(Gem::new(&gem_sprite, pos)));
-                        Tile::new(None, TileCollision::Passable)
-                    },
-                    '-' => {
-                        // Floating platform
-                        Tile::load(phi, "assets/tiles/platform.png", TileCollision::Platform)
-                    },
-                    'A' => {
-                        // TODO: add the enemy to the enemy list
-                        Tile::new(None, TileCollision::Passable)
-                    },
-                    'B' => {
-                        // TODO: add the enemy to the enemy list
-                        Tile::new(None, TileCollision::Passable)
-                    },
-                    'C' => {
-                        // TODO: add the enemy to the enemy list
-                        Tile::new(None, TileCollision::Passable)
-                    },
-                    'D' => {
-                        // TODO: add the enemy to the enemy list
-                        Tile::new(None, TileCollision::Passable)
-                    },
-                    '~' => {
-                        // Platform block
-                        GameLevel::load_random_tile(phi, "assets/tiles/blockb", 2, TileCollision::Platform)
-                    },
-                    ':' => {
-                        // Passable block
-                        GameLevel::load_random_tile(phi, "assets/tiles/blockb", 2, TileCollision::Passable)
-                    },
-                    '1' => {
-                        // player start point
-                        start.x = xth as f64 * TILE_WIDTH + TILE_WIDTH / 2.0;
-                        start.y = yth as f64 * TILE_HEIGHT - TILE_HEIGHT - 1.0;
-                        Tile::new(None, TileCollision::Passable)
-                    },
-                    '#' => {
-                        // Impassable block
-                        GameLevel::load_random_tile(phi, "assets/tiles/blocka", 7, TileCollision::Impassable)
-                    },
-                    _ => { panic!("Unsupported tile type '{}'", tile_type); }
+                let def = match tileset.get(tile_type) {
+                    Some(def) => def,
+                    None => {
+                        error!("{}:{}:{}: unsupported tile type '{}'",
+                               path, file.tiles_line + yth, xth + 1, tile_type);
+                        panic!("Unsupported tile type '{}'", tile_type);
+                    }
+                };
+
+                if def.flags.start {
+                    start = GameLevel::start_position(&meta, xth, yth);
+                }
+                if def.flags.exit {
+                    exit = GameLevel::tile_center(&meta, xth, yth);
+                }
+                if def.flags.gem {
+                    gems.push(GameLevel::tile_center(&meta, xth, yth));
+                }
+                if def.flags.enemy {
+                    enemies.push((tile_type, GameLevel::tile_bottom(&meta, xth, yth)));
+                }
+                if def.flags.checkpoint {
+                    checkpoints.push((xth, yth));
+                }
+
+                // the auto-tiled tiles pick among the images of their
+                // neighbour mask
+                let sprites = tileset.sprites_at(lines, xth, yth);
+                xvec.push(match sprites.len() {
+                    0 => Tile::new(None, def.collision),
+                    1 => Tile::load(phi, &sprites[0], def.collision),
+                    n => {
+                        // the variant recorded in the file wins over the
+                        // random one
+                        let x = match file.variant(xth, yth) {
+                            Some(Ok(x)) if x < n => x,
+                            _ => rng.gen_range(0, n),
+                        };
+                        row_variants.push(variant_char(x));
+                        Tile::load(phi, &sprites[x], def.collision)
+                    }
                 });
+                if sprites.len() < 2 {
+                    row_variants.push('.');
+                }
             }
             yvec.push(xvec);
+            variants.push(row_variants);
         }
 
-        GameLevel {
-            player: RefCell::new(Player::new(phi, start.x, start.y)),
-            layers: vec![
-                Sprite::load(&mut phi.renderer, "assets/background0.png").unwrap(),
-                Sprite::load(&mut phi.renderer, "assets/background1.png").unwrap(),
-                Sprite::load(&mut phi.renderer, "assets/background2.png").unwrap(),
-            ],
+        let mut background = Vec::new();
+        let mut foreground = Vec::new();
+        let mut layer_variants = Vec::with_capacity(file.layers.len());
+        for layer in &file.layers {
+            let (tiles, variants) = GameLevel::decoration(phi, path, &tileset, layer, &mut rng);
+            if layer.foreground {
+                foreground.push(tiles);
+            } else {
+                background.push(tiles);
+            }
+            layer_variants.push(variants);
+        }
+
+        let platforms = match file.platforms() {
+            Ok(platforms) => platforms,
+            Err(e) => {
+                error!("{}: {}", path, e);
+                panic!("Cannot load the platforms of level {}: {}", path, e);
+            }
+        };
+        let platforms = platforms.iter().map(|platform| {
+            let def = match tileset.get(platform.tile) {
+                Some(def) => def,
+                None => {
+                    error!("{}: unsupported platform tile type '{}'", path, platform.tile);
+                    panic!("Unsupported tile type '{}'", platform.tile);
+                }
+            };
+
+            let tiles = (0..platform.width).map(|_| match def.sprites.len() {
+                0 => Tile::new(None, def.collision),
+                n => Tile::load(phi, &def.sprites[rng.gen_range(0, n)], def.collision),
+            }).collect();
+            MovingPlatform::new(&meta, platform, tiles, def.collision)
+        }).collect();
+
+        GameLevel::assemble(phi, LevelParts {
+            meta: meta,
+            background: background,
             tiles: yvec,
+            foreground: foreground,
+            gems: gems,
+            enemies: enemies,
+            checkpoints: checkpoints,
+            platforms: platforms,
+            start: start,
+            exit: exit,
+            source: Some(GameLevel::source(file, variants, layer_variants)),
+        }, seed)
+    }
+
+    // the tiles of a decoration layer of a text level and their variants,
+    // recorded like the ones of the level tiles
+    fn decoration(phi: &mut Phi, path: &str, tileset: &Tileset, layer: &DecorationLayer,
+                  rng: &mut StdRng) -> (Vec<Vec<Tile>>, Vec<String>) {
+        let mut tiles = Vec::with_capacity(layer.rows.len());
+        let mut variants = Vec::with_capacity(layer.rows.len());
+        for (yth, row) in layer.rows.iter().enumerate() {
+            let mut xvec = Vec::with_capacity(row.len());
+            let mut row_variants = String::with_capacity(row.len());
+            for (xth, tile_type) in row.chars().enumerate() {
+                if tileset.get(tile_type).is_none() {
+                    error!("{}:{}:{}: unsupported tile type '{}'",
+                           path, layer.line + yth, xth + 1, tile_type);
+                    panic!("Unsupported tile type '{}'", tile_type);
+                }
+
+                let sprites = tileset.sprites_at(&layer.rows, xth, yth);
+                xvec.push(match sprites.len() {
+                    0 => Tile::new(None, TileCollision::Passable),
+                    1 => Tile::load(phi, &sprites[0], TileCollision::Passable),
+                    n => {
+                        let x = match layer.variant(xth, yth) {
+                            Some(Ok(x)) if x < n => x,
+                            _ => rng.gen_range(0, n),
+                        };
+                        row_variants.push(variant_char(x));
+                        Tile::load(phi, &sprites[x], TileCollision::Passable)
+                    }
+                });
+                if sprites.len() < 2 {
+                    row_variants.push('.');
+                }
+            }
+            tiles.push(xvec);
+            variants.push(row_variants);
+        }
+        (tiles, variants)
+    }
+
+    // the level file to save, with the variants drawn for the tiles and the
+    // layers which have a variants section, so that saving the other ones
+    // gives back the same file
+    fn source(file: LevelFile, variants: Vec<String>, layer_variants: Vec<Vec<String>>)
+              -> LevelFile {
+        let mut source = LevelFile {
+            variants: if file.variants_line != 0 { variants } else { Vec::new() },
+            ..file
+        };
+        for (layer, variants) in source.layers.iter_mut().zip(layer_variants) {
+            if layer.variants_line != 0 {
+                layer.variants = variants;
+            }
+        }
+        source
+    }
+
+    fn load_tiled(phi: &mut Phi, path: &str, seed: Option<u32>) -> GameLevel {
+        let map = TiledMap::load(path)
+            .and_then(|map| map.meta().map(|meta| (map, meta)));
+        let (map, meta) = match map {
+            Ok(result) => result,
+            Err(e) => {
+                error!("{}: {}", path, e);
+                panic!("Cannot load level {}: {}", path, e);
+            }
+        };
+
+        let (seed, _) = GameLevel::seed_rng(seed, &meta);
+        debug!("level {} is {}x{} tiles, {} tile layers",
+               path, map.width, map.height, map.layers.len());
+
+        let empty = || -> Vec<Vec<Tile>> {
+            (0..map.height).map(|_| {
+                (0..map.width).map(|_| Tile::new(None, TileCollision::Passable)).collect()
+            }).collect()
+        };
+        let mut tiles = empty();
+        let mut background = Vec::new();
+        let mut foreground = Vec::new();
+
+        // the collision layers are flattened: the sprite of the topmost layer
+        // is drawn and the most restrictive collision wins
+        let mut images: HashMap<String, Sprite> = HashMap::new();
+        for layer in map.layers.iter().filter(|l| l.visible) {
+            let depth = layer.depth().unwrap_or_else(|name| {
+                warn!("{}: layer '{}' has the unknown depth '{}'", path, layer.name, name);
+                LayerDepth::Collision
+            });
+            let grid = match depth {
+                LayerDepth::Collision => &mut tiles,
+                LayerDepth::Background => {
+                    background.push(empty());
+                    background.last_mut().unwrap()
+                },
+                LayerDepth::Foreground => {
+                    foreground.push(empty());
+                    foreground.last_mut().unwrap()
+                },
+            };
+
+            for (i, &gid) in layer.gids.iter().enumerate() {
+                if gid == 0 {
+                    continue;
+                }
+
+                let (tileset, id) = match map.tile(gid) {
+                    Some(tile) => tile,
+                    None => {
+                        warn!("{}: layer '{}' uses the unknown tile {}", path, layer.name, gid);
+                        continue;
+                    }
+                };
+
+                let (image, region) = match tileset.source(id) {
+                    Some(source) => source,
+                    None => {
+                        warn!("{}: tile {} has no image", path, gid);
+                        continue;
+                    }
+                };
+
+                if !images.contains_key(&image) {
+                    let sprite = Sprite::load(&phi.renderer, &image).unwrap_or_else(|| {
+                        panic!("Tileset image {} not found!", image)
+                    });
+                    images.insert(image.clone(), sprite);
+                }
+
+                let tile = &mut grid[i / map.width][i % map.width];
+                tile.sprite = region.map_or_else(|| Some(images[&image].clone()),
+                                                 |r| images[&image].region(r));
+                if depth != LayerDepth::Collision {
+                    continue;
+                }
+
+                let collision = match tileset.property(id, "collision") {
+                    None => TileCollision::Passable,
+                    Some(name) => parse_collision(name).unwrap_or_else(|| {
+                        warn!("{}: tile {} has the unknown collision '{}'", path, gid, name);
+                        TileCollision::Passable
+                    }),
+                };
+                if collision_rank(collision) > collision_rank(tile.collision) {
+                    tile.collision = collision;
+                }
+            }
+        }
+
+        let mut gems = Vec::new();
+        let mut enemies = Vec::new();
+        let mut checkpoints = Vec::new();
+        let mut start = None;
+        let mut exit = None;
+        let mut exit_tile = (0, 0);
+        for object in &map.objects {
+            let center = object.center();
+            let xth = glm::floor(center.x / meta.tile_width).max(0.0) as usize;
+            let yth = glm::floor(center.y / meta.tile_height).max(0.0) as usize;
+
+            match object.kind.as_str() {
+                "gem" => gems.push(center),
+                "start" => start = Some(GameLevel::start_position(&meta, xth, yth)),
+                "exit" => {
+                    exit = Some(GameLevel::tile_center(&meta, xth, yth));
+                    exit_tile = (xth, yth);
+                },
+                "enemy" => {
+                    let kind = object.properties.iter().rev()
+                        .find(|p| p.key == "kind")
+                        .and_then(|p| p.value.chars().next())
+                        .unwrap_or('A');
+                    enemies.push((kind, GameLevel::tile_bottom(&meta, xth, yth)));
+                },
+                "checkpoint" => checkpoints.push((xth, yth)),
+                other => warn!("{}: ignoring the object '{}' of type '{}'",
+                               path, object.name, other),
+            }
+        }
+
+        let (start, exit) = match (start, exit) {
+            (Some(start), Some(exit)) => (start, exit),
+            _ => {
+                let missing = if start.is_none() { "start" } else { "exit" };
+                error!("{}: missing {} object", path, missing);
+                panic!("Level {} has no {} object", path, missing);
+            }
+        };
+
+        // the exit is drawn like the one of the text levels, unless the map
+        // draws its own
+        let tileset = Tileset::load(DEFAULT_TILESET);
+        let exit_image = tileset.as_ref().ok()
+            .and_then(|tileset| tileset.tiles.iter().find(|t| t.flags.exit))
+            .and_then(|def| def.sprites.first());
+        if let (Some(image), Some(tile)) = (exit_image, tiles.get_mut(exit_tile.1)
+                                            .and_then(|row| row.get_mut(exit_tile.0))) {
+            if tile.sprite.is_none() {
+                tile.sprite = Sprite::load(&phi.renderer, image);
+            }
+        }
+
+        let source = tileset.and_then(|tileset| map.to_text(&tileset));
+        let source = match source {
+            Ok(file) => Some(file),
+            Err(e) => {
+                warn!("{}: cannot be written as a text level: {}", path, e);
+                None
+            }
+        };
+
+        GameLevel::assemble(phi, LevelParts {
+            meta: meta,
+            background: background,
+            tiles: tiles,
+            foreground: foreground,
             gems: gems,
+            enemies: enemies,
+            checkpoints: checkpoints,
+            platforms: Vec::new(),
             start: start,
             exit: exit,
+            source: source,
+        }, seed)
+    }
+
+    // the seed given by the caller wins over the one of the level, a random
+    // one is used when both are missing
+    fn seed_rng(seed: Option<u32>, meta: &LevelMeta) -> (u32, StdRng) {
+        let seed = seed.or(meta.seed).unwrap_or_else(::rand::random::<u32>);
+        info!("level seed: {}", seed);
+        (seed, StdRng::from_seed(&[seed as usize]))
+    }
+
+    fn tile_center(meta: &LevelMeta, xth: usize, yth: usize) -> glm::Vector2<f64> {
+        glm::Vector2::new(
+            xth as f64 * meta.tile_width + meta.tile_width / 2.0,
+            yth as f64 * meta.tile_height + meta.tile_height / 2.0,
+        )
+    }
+
+    // the middle of the bottom of a tile, where the enemies stand
+    fn tile_bottom(meta: &LevelMeta, xth: usize, yth: usize) -> glm::Vector2<f64> {
+        glm::Vector2::new(
+            xth as f64 * meta.tile_width + meta.tile_width / 2.0,
+            (yth + 1) as f64 * meta.tile_height,
+        )
+    }
+
+    // the player stands on the bottom of the start tile
+    fn start_position(meta: &LevelMeta, xth: usize, yth: usize) -> glm::Vector2<f64> {
+        glm::Vector2::new(
+            xth as f64 * meta.tile_width + meta.tile_width / 2.0,
+            yth as f64 * meta.tile_height - meta.tile_height - 1.0,
+        )
+    }
+
+    fn assemble(phi: &mut Phi, parts: LevelParts, seed: u32) -> GameLevel {
+        let meta = parts.meta;
+        if let Some(ref name) = meta.name {
+            info!("level name: {}", name);
+        }
+        if let Some(ref music) = meta.music {
+            warn!("music {} ignored, audio is not supported", music);
+        }
+
+        let layers = meta.backgrounds.iter().map(|background| {
+            Sprite::load(&phi.renderer, background).unwrap_or_else(|| {
+                panic!("Background {} not found!", background)
+            })
+        }).collect();
+
+        let gem_sprite = Sprite::load(&phi.renderer, "assets/sprites/gem.png").unwrap();
+        let radius = meta.tile_width / 3.0;
+        let gems: Vec<Box<Gem>> = parts.gems.iter()
+            .map(|&pos| Box::new(Gem::new(&gem_sprite, pos, radius)))
+            .collect();
+
+        // the enemies of a kind share their sprites
+        let mut enemy_sprites: HashMap<char, EnemySprites> = HashMap::new();
+        let mut enemies = Vec::with_capacity(parts.enemies.len());
+        for &(kind, feet) in &parts.enemies {
+            if !enemy_sprites.contains_key(&kind) {
+                let sprites = EnemySprites::load(phi, kind);
+                enemy_sprites.insert(kind, sprites);
+            }
+            enemies.push(Enemy::new(&enemy_sprites[&kind], kind, feet));
+        }
+
+        let checkpoint_sprite = Sprite::load(&phi.renderer, CHECKPOINT_SPRITE).unwrap();
+        let active_sprite = Sprite::load(&phi.renderer, CHECKPOINT_ACTIVE_SPRITE).unwrap();
+        let checkpoints = parts.checkpoints.iter().map(|&(xth, yth)| Checkpoint {
+            bounds: Rectangle {
+                x: xth as f64 * meta.tile_width,
+                y: yth as f64 * meta.tile_height,
+                w: meta.tile_width,
+                h: meta.tile_height,
+            },
+            spawn: GameLevel::start_position(&meta, xth, yth),
+            active: false,
+            sprite: checkpoint_sprite.clone(),
+            active_sprite: active_sprite.clone(),
+        }).collect();
+
+        let width = parts.tiles.iter().map(|row| row.len()).max().unwrap_or(0);
+        let height = parts.tiles.len();
+
+        GameLevel {
+            player: RefCell::new(Player::new(phi, parts.start.x, parts.start.y)),
+            layers: layers,
+            background: parts.background,
+            tiles: parts.tiles,
+            foreground: parts.foreground,
+            total_gems: gems.len(),
+            spawn_gems: gems.clone(),
+            spawn_enemies: enemies.clone(),
+            enemies: enemies,
+            checkpoints: checkpoints,
+            platforms: parts.platforms,
+            respawn_point: parts.start,
+            spawn_score: 0,
+            spawn_time: meta.time_limit,
+            gems: gems,
+            collected: Vec::new(),
+            exit: parts.exit,
+            reached_exit: false,
+            complete: false,
+            dead: false,
+            time_left: meta.time_limit,
+            score: 0,
             width: width,
             height: height,
+
+            meta: meta,
+            seed: seed,
+            source: parts.source,
+            seed_sprite: if DEBUG {
+                phi.ttf_str_sprite(&format!("seed: {}", seed), "assets/fonts/liberation-mono.ttf",
+                                   20, pixels::Color::RGB(255, 0, 255))
+            } else {
+                None
+            },
         }
     }
 
-    fn load_random_tile(phi: &mut Phi, base: &str, count: usize, collision: TileCollision) -> Tile {
-        let x = ::rand::random::<usize>() % count;
-        let name = format!("{}{}.png", base, x);
-
-        Tile::load(phi, &name, collision)
+    /// Save the level in the text format, loading the file gives back the
+    /// same level.
+    ///
+    /// A text level is written as it was loaded, with the variants drawn
+    /// for the tiles when it has a `[variants]` section: saving a level
+    /// loaded from a file written by this function gives back the same file.
+    /// A Tiled map is converted by `TiledMap::to_text` with the default
+    /// tileset.
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        match self.source {
+            Some(ref file) => file.save(path),
+            None => Err(io::Error::new(io::ErrorKind::Other,
+                                       "the level can't be written as a text level")),
+        }
     }
 
     pub fn get_collision(&self, x: i32, y: i32) -> TileCollision {
@@ -153,28 +680,151 @@ impl GameLevel {
         }
     }
 
-    pub fn update(&mut self, phi: &mut Phi, elapsed: f64) {
+    /// Push `bounds` out of the tiles and the moving platforms it overlaps,
+    /// the way the player and the enemies collide with the level.
+    /// `previous_bottom` is the bottom of the bounds on the previous frame:
+    /// the platforms only stop what comes from above.
+    ///
+    /// Returns how far the bounds were pushed and whether they stand on a
+    /// tile.
+    pub fn collide(&self, bounds: Rectangle, previous_bottom: f32) -> (glm::Vector2<f64>, bool) {
+        let mut bound_rect = bounds;
+        let (tile_width, tile_height) = (self.meta.tile_width, self.meta.tile_height);
+
+        // the values can go out of bounds but we are saved by the fact that
+        // get_collision() handles out of bounds values
+        let left_tile = glm::floor(bound_rect.x / tile_width) as i32;
+        let top_tile = glm::floor(bound_rect.y / tile_height) as i32;
+        let right_tile = glm::ceil((bound_rect.x + bound_rect.w) / tile_width) as i32;
+        let bottom_tile = glm::ceil((bound_rect.y + bound_rect.h) / tile_height) as i32;
+
+        let mut on_ground = false;
+        let mut tile_bounds = Rectangle {
+            x: left_tile as f64 * tile_width,
+            y: top_tile as f64 * tile_height,
+            w: tile_width, h: tile_height
+        };
 
-        if false || false {
-            // TODO: player dead or timer expired
-        } else if false {
-            // TODO: player reached the exit
+        for yth in top_tile..bottom_tile {
+            for xth in left_tile..right_tile {
+                let collision = self.get_collision(xth, yth);
+                push_out(&mut bound_rect, &mut on_ground, &tile_bounds, tile_bounds.y,
+                         collision, previous_bottom);
+                tile_bounds.x += tile_width;
+            }
+            tile_bounds.x = left_tile as f64 * tile_width;
+            tile_bounds.y += tile_height;
+        }
+
+        // what stood on a platform before it moved still stands on it
+        for platform in &self.platforms {
+            let previous_top = platform.bounds.y - platform.delta.y + PLATFORM_TOLERANCE;
+            push_out(&mut bound_rect, &mut on_ground, &platform.bounds, previous_top,
+                     platform.collision, previous_bottom);
+        }
+
+        (glm::Vector2::new(bound_rect.x - bounds.x, bound_rect.y - bounds.y), on_ground)
+    }
+
+    /// How far the moving platform `bounds` stands on moved on the last
+    /// frame, nothing when it doesn't stand on one.
+    pub fn carried(&self, bounds: Rectangle) -> glm::Vector2<f64> {
+        let bottom = bounds.y + bounds.h;
+        self.platforms.iter()
+            .find(|platform| {
+                let previous = platform.previous_bounds();
+                (bottom - previous.y).abs() <= PLATFORM_TOLERANCE &&
+                    bounds.x < previous.x + previous.w && previous.x < bounds.x + bounds.w
+            })
+            .map_or(glm::Vector2::new(0.0, 0.0), |platform| platform.delta)
+    }
+
+    /// Whether `bounds` is stuck inside an impassable tile or platform, where
+    /// a moving platform pushed it after the tiles pushed it out.
+    pub fn crushed(&self, bounds: Rectangle) -> bool {
+        let inner = Rectangle {
+            x: bounds.x + PLATFORM_TOLERANCE,
+            y: bounds.y + PLATFORM_TOLERANCE,
+            w: bounds.w - 2.0 * PLATFORM_TOLERANCE,
+            h: bounds.h - 2.0 * PLATFORM_TOLERANCE,
+        };
+        let (tile_width, tile_height) = (self.meta.tile_width, self.meta.tile_height);
+
+        let left_tile = glm::floor(inner.x / tile_width) as i32;
+        let top_tile = glm::floor(inner.y / tile_height) as i32;
+        let right_tile = glm::ceil((inner.x + inner.w) / tile_width) as i32;
+        let bottom_tile = glm::ceil((inner.y + inner.h) / tile_height) as i32;
+
+        (top_tile..bottom_tile).any(|yth| (left_tile..right_tile).any(|xth| {
+            self.get_collision(xth, yth) == TileCollision::Impassable
+        })) || self.platforms.iter().any(|platform| {
+            platform.collision == TileCollision::Impassable && platform.bounds.overlaps(inner)
+        })
+    }
+
+    pub fn update(&mut self, phi: &mut Phi, elapsed: f64) {
+        // the collect effects play to the end whatever happens to the player
+        let collected = ::std::mem::replace(&mut self.collected, vec![]);
+        self.collected = collected.into_iter().filter_map(|gem| gem.update(elapsed)).collect();
+
+        if !self.player.borrow().alive {
+            // the view decides whether the player comes back once the die
+            // animation is over
+            if self.player.borrow_mut().play_once(elapsed) && !self.dead {
+                info!("the player is dead");
+                self.dead = true;
+            }
+        } else if self.time_up() {
+            // play stops until the level is restarted
+        } else if self.reached_exit {
+            // the level is complete once the celebrate animation is over
+            if self.player.borrow_mut().play_once(elapsed) && !self.complete {
+                info!("level complete");
+                self.complete = true;
+            }
         } else {
-            // TODO: update the timer
+            self.time_left = (self.time_left - elapsed).max(0.0);
+            if self.time_up() {
+                info!("time's up");
+                return
+            }
+
+            // the platforms move before the player they carry
+            for platform in &mut self.platforms {
+                platform.update(elapsed);
+            }
 
-            // TODO: update the player
             {
                 let world = &*self;
-                self.player.borrow_mut().update(phi, world, elapsed);
+                let mut player = self.player.borrow_mut();
+                player.update(phi, world, elapsed);
+
+                // a platform going up may push the player into the ceiling
+                if self.crushed(player.bounding_rect()) {
+                    info!("the player was crushed");
+                    player.kill();
+                }
+                ::crash::set_player(player.pos.x, player.pos.y, player.vel.x, player.vel.y);
+
+                // the player must stand in front of the exit
+                let bounds = player.bounding_rect();
+                if player.on_ground &&
+                    bounds.x <= self.exit.x && self.exit.x <= bounds.x + bounds.w &&
+                    bounds.y <= self.exit.y && self.exit.y <= bounds.y + bounds.h {
+                    info!("the player reached the exit");
+                    self.reached_exit = true;
+                    player.celebrate();
+                }
             }
 
             // update the gems
+            let bounds = self.player.borrow().bounding_rect();
             let mut old_gems = ::std::mem::replace(&mut self.gems, vec![]);
-            while let Some(mut gem) = old_gems.pop() {
-                // TODO: instead of false check for intersection with player
-                if false {
-                    // TODO: add the gem points to the score
-                    // TODO: collect the gem
+            while let Some(gem) = old_gems.pop() {
+                if gem.bounding_circle().intersects(&bounds) {
+                    debug!("gem collected, {} points", gem.points);
+                    self.score += gem.points;
+                    self.collected.push(Box::new(CollectedGem::new(&gem)));
                 } else {
                     if let Some(gem) = gem.update(phi, elapsed) {
                         self.gems.push(gem);
@@ -182,9 +832,105 @@ impl GameLevel {
                 }
             }
 
-            // TODO: falling off the bottom kills the player
+            // update the enemies, the ones falling off the level are gone
+            let bottom = self.height as f64 * self.meta.tile_height;
+            let mut enemies = ::std::mem::replace(&mut self.enemies, vec![]);
+            for enemy in &mut enemies {
+                enemy.update(self, elapsed);
+            }
+            enemies.retain(|enemy| !enemy.gone && enemy.bounding_rect().y < bottom);
+            self.enemies = enemies;
+
+            // touching a checkpoint moves the respawn there, with the gems,
+            // the enemies left, the score and the time as they are now
+            let bounds = self.player.borrow().bounding_rect();
+            if let Some(checkpoint) = self.checkpoints.iter_mut()
+                .find(|checkpoint| !checkpoint.active && checkpoint.bounds.overlaps(bounds)) {
+                info!("checkpoint reached at {}, {}", checkpoint.spawn.x, checkpoint.spawn.y);
+                checkpoint.active = true;
+                self.respawn_point = checkpoint.spawn;
+                self.spawn_gems = self.gems.clone();
+                self.spawn_enemies = self.enemies.iter()
+                    .filter(|enemy| !enemy.defeated)
+                    .map(|enemy| Enemy::new(&enemy.sprites, enemy.kind, enemy.home))
+                    .collect();
+                self.spawn_score = self.score;
+                self.spawn_time = self.time_left;
+            }
+
+            // falling off the bottom or touching an enemy kills the player,
+            // unless landing on the enemy which is defeated
+            let mut player = self.player.borrow_mut();
+            let bounds = player.bounding_rect();
+            if bounds.y >= bottom {
+                info!("the player fell off the level");
+                player.kill();
+            }
+
+            // the enemies are all checked against the fall of the player
+            // before it bounces, so that landing on two of them at once
+            // defeats both
+            if player.alive {
+                let mut stomped = false;
+                let mut killer = None;
+                let touching = self.enemies.iter_mut()
+                    .filter(|enemy| !enemy.defeated && enemy.bounding_rect().overlaps(bounds));
+                for enemy in touching {
+                    if enemy.stomped_by(&player) {
+                        info!("enemy {} defeated, {} points", enemy.kind, ENEMY_POINTS);
+                        enemy.defeat();
+                        self.score += ENEMY_POINTS;
+                        stomped = true;
+                    } else if killer.is_none() {
+                        killer = Some(enemy.kind);
+                    }
+                }
 
-            // TODO: update the enemies
+                if stomped {
+                    player.bounce();
+                }
+                if let Some(kind) = killer {
+                    info!("the player was killed by an enemy {}", kind);
+                    player.kill();
+                }
+            }
+        }
+    }
+
+    /// Bring the player back to the start or the last checkpoint reached,
+    /// with the gems, the enemies, the score and the time left as they were
+    /// there.
+    pub fn respawn(&mut self) {
+        info!("respawning the player");
+        self.dead = false;
+        self.player.borrow_mut().reset(self.respawn_point);
+        self.gems = self.spawn_gems.clone();
+        self.enemies = self.spawn_enemies.clone();
+        self.score = self.spawn_score;
+        self.time_left = self.spawn_time;
+    }
+
+    /// Play stops when the time runs out, until the level is restarted.
+    pub fn time_up(&self) -> bool {
+        self.time_left <= 0.0
+    }
+
+    /// Points earned by the time left.
+    pub fn time_bonus(&self) -> u32 {
+        self.time_left.ceil() as u32 * POINTS_PER_SECOND
+    }
+
+    fn render_tiles(&self, phi: &mut Phi, tiles: &[Vec<Tile>]) {
+        let (tile_width, tile_height) = (self.meta.tile_width, self.meta.tile_height);
+        let mut rect = Rectangle::with_size(tile_width, tile_height);
+        for y in 0..tiles.len() {
+            for x in 0..tiles[y].len() {
+                let srect = rect.to_sdl();
+                tiles[y][x].render(&mut phi.renderer, &srect, RenderFx::None);
+                rect.x += tile_width;
+            }
+            rect.x = 0.0;
+            rect.y += tile_height;
         }
     }
 
@@ -199,27 +945,47 @@ impl GameLevel {
             layer.render(&mut phi.renderer, &dest.to_sdl(), RenderFx::None);
         }
 
-        // Draw the tiles
-        let mut rect = Rectangle::with_size(TILE_WIDTH, TILE_HEIGHT);
-        for y in 0..self.tiles.len() {
-            for x in 0..self.tiles[y].len() {
-                let srect = rect.to_sdl();
-                self.tiles[y][x].render(&mut phi.renderer, &srect, RenderFx::None);
-                rect.x += TILE_WIDTH;
-            }
-            rect.x = 0.0;
-            rect.y += TILE_HEIGHT;
+        // Draw the tiles, in front of the background decoration
+        for tiles in &self.background {
+            self.render_tiles(phi, tiles);
+        }
+        self.render_tiles(phi, &self.tiles);
+        for platform in &self.platforms {
+            platform.render(phi);
+        }
+
+        for checkpoint in &self.checkpoints {
+            checkpoint.render(phi);
         }
 
         // Render the gems
         for gem in &self.gems {
             gem.render(phi);
         }
+        for gem in &self.collected {
+            gem.render(phi);
+        }
 
         // render the player
         self.player.borrow().render(phi);
 
         // render the enemies
+        for enemy in &self.enemies {
+            enemy.render(phi);
+        }
+
+        // the foreground decoration hides the player
+        for tiles in &self.foreground {
+            self.render_tiles(phi, tiles);
+        }
+
+        // report the seed next to the FPS counter
+        if let Some(ref sprite) = self.seed_sprite {
+            let (w, h) = sprite.size();
+            let (_, win_h) = phi.output_size();
+            let dest = Rectangle { x: 150.0, y: win_h - h - 10.0, w: w, h: h };
+            sprite.render(&mut phi.renderer, &dest.to_sdl(), RenderFx::None);
+        }
     }
 }
 
@@ -227,19 +993,6 @@ const PLAYER_WIDTH: f64 = 64.0;
 const PLAYER_HEIGHT: f64 = 64.0;
 const PLAYER_FPS: f64 = 15.0;
 
-// horizontal movements
-const PLAYER_MOVE_ACCEL: f32 = 13000.0_f32;
-const PLAYER_MAX_SPEED: f32 = 1750.0_f32;
-const PLAYER_GROUND_DRAG: f32 = 0.48_f32;
-const PLAYER_AIR_DRAG: f32 = 0.58_f32;
-
-// vertical movements
-const PLAYER_MAX_JUMP_TIME: f32 = 0.35_f32;
-const PLAYER_JUMP_LAUNCH_VEL: f32 = -3500.0_f32;
-const PLAYER_GRAVITY_ACCEL: f32 = 3400.0_f32;
-const PLAYER_MAX_FALL_SPEED: f32 = 550.0_f32;
-const PLAYER_JUMP_POWER: f32 = 0.14_f32;
-
 #[derive(Clone, Copy)]
 enum PlayerFrame {
     Idle = 0,
@@ -249,6 +1002,7 @@ enum PlayerFrame {
     Die = 4,
 }
 
+#[derive(Clone, Copy, PartialEq)]
 enum PlayerDirection {
     Left,
     Right,
@@ -258,6 +1012,9 @@ struct Player {
     pos: glm::Vector2<f64>,
     vel: glm::Vector2<f32>,
 
+    // the player ignores the input while the die animation plays
+    alive: bool,
+
     // jumping state
     on_ground: bool,
     is_jumping: bool,
@@ -315,6 +1072,7 @@ impl Player {
             pos: glm::Vector2::new(x, y),
             vel: glm::Vector2::new(64.0, 64.0),
 
+            alive: true,
             on_ground: true,
             is_jumping: false,
             jump_time: 0.0_f32,
@@ -327,6 +1085,46 @@ impl Player {
         }
     }
 
+    // stand still at `pos`, alive again
+    pub fn reset(&mut self, pos: glm::Vector2<f64>) {
+        self.pos = pos;
+        self.vel = glm::Vector2::new(0.0, 0.0);
+        self.alive = true;
+        self.on_ground = true;
+        self.is_jumping = false;
+        self.jump_time = 0.0;
+        self.previous_bottom = 0.0;
+        self.current = PlayerFrame::Idle;
+    }
+
+    pub fn kill(&mut self) {
+        self.alive = false;
+        self.stop(PlayerFrame::Die);
+    }
+
+    // jump off an enemy the player landed on
+    pub fn bounce(&mut self) {
+        self.vel.y = STOMP_BOUNCE_VEL;
+        self.on_ground = false;
+        self.jump_time = 0.0;
+    }
+
+    pub fn celebrate(&mut self) {
+        self.stop(PlayerFrame::Celebrate);
+    }
+
+    // stand still and start `animation`
+    fn stop(&mut self, animation: PlayerFrame) {
+        self.vel = glm::Vector2::new(0.0, 0.0);
+        self.current = animation;
+        self.sprites[animation as usize].reset();
+    }
+
+    // play the die or celebrate animation, returns whether it is over
+    pub fn play_once(&mut self, elapsed: f64) -> bool {
+        self.sprites[self.current as usize].add_time_once(elapsed)
+    }
+
     pub fn bounding_rect(&self) -> Rectangle {
         Rectangle {
             x: self.pos.x + self.local_bounds.x,
@@ -339,6 +1137,11 @@ impl Player {
             use self::PlayerFrame::*;
             use self::PlayerDirection::*;
 
+            // ride the moving platform under the player
+            if self.on_ground {
+                self.pos = self.pos + level.carried(self.bounding_rect());
+            }
+
             // apply physics
             let dx = if phi.events.key_left {
                 -1.0f32
@@ -351,8 +1154,9 @@ impl Player {
             // the base velocity is a combination of horizontal movement control
             // and acceleration downwards due to gravity.
             self.vel.x += dx * PLAYER_MOVE_ACCEL * elapsed as f32;
+            let gravity = level.meta.gravity.unwrap_or(PLAYER_GRAVITY_ACCEL);
             self.vel.y = glm::clamp(
-                self.vel.y + PLAYER_GRAVITY_ACCEL * elapsed as f32,
+                self.vel.y + gravity * elapsed as f32,
                 -PLAYER_MAX_FALL_SPEED,
                 PLAYER_MAX_FALL_SPEED
             );
@@ -364,9 +1168,7 @@ impl Player {
                 }
 
                 if 0.0_f32 < self.jump_time && self.jump_time <= PLAYER_MAX_JUMP_TIME {
-                    self.vel.y = PLAYER_JUMP_LAUNCH_VEL *
-                        (1.0f32 - glm::pow(self.jump_time / PLAYER_MAX_JUMP_TIME,
-                                           PLAYER_JUMP_POWER));
+                    self.vel.y = jump_velocity(self.jump_time);
                 } else {
                     self.jump_time = 0.0_f32;
                 }
@@ -382,48 +1184,9 @@ impl Player {
             self.pos.y = self.pos.y + self.vel.y as f64 * elapsed;
 
         // handle collisions
-        let mut bound_rect = self.bounding_rect();
-
-        // the values can go out of bounds but we are saved by the fact that
-        // level.get_collision() handles out of bounds values
-        let left_tile = glm::floor(bound_rect.x / TILE_WIDTH) as i32;
-        let top_tile = glm::floor(bound_rect.y / TILE_HEIGHT) as i32;
-        let right_tile = glm::ceil((bound_rect.x + bound_rect.w) / TILE_WIDTH) as i32;
-        let bottom_tile = glm::ceil((bound_rect.y + bound_rect.h) / TILE_HEIGHT) as i32;
-
-        self.on_ground = false;
-        let mut tile_bounds = Rectangle {
-            x: left_tile as f64 * TILE_WIDTH,
-            y: top_tile as f64 * TILE_HEIGHT,
-            w: TILE_WIDTH, h: TILE_HEIGHT
-        };
-
-        for yth in top_tile..bottom_tile {
-            for xth in left_tile..right_tile {
-                let collision = level.get_collision(xth, yth);
-
-                if collision != TileCollision::Passable {
-                    if let Some(depth) = bound_rect.intersection_depth(&tile_bounds) {
-                        if depth.y.abs() < depth.x.abs() || collision == TileCollision::Platform {
-                            if self.previous_bottom <= tile_bounds.y as f32 {
-                                self.on_ground = true;
-                            }
-
-                            if collision == TileCollision::Impassable || self.on_ground {
-                                self.pos.y += depth.y;
-                                bound_rect.y += depth.y;
-                            }
-                        } else if collision == TileCollision::Impassable {
-                            self.pos.x += depth.x;
-                            bound_rect.x += depth.x;
-                        }
-                    }
-                }
-                tile_bounds.x += TILE_WIDTH;
-            }
-            tile_bounds.x = left_tile as f64 * TILE_WIDTH;
-            tile_bounds.y += TILE_HEIGHT;
-        }
+        let (offset, on_ground) = level.collide(self.bounding_rect(), self.previous_bottom);
+        self.pos = self.pos + offset;
+        self.on_ground = on_ground;
         self.previous_bottom = (self.pos.y + PLAYER_HEIGHT) as f32;
 
         // reset the velocity if a collision stopped the player
@@ -472,9 +1235,407 @@ impl Player {
     }
 }
 
+const ENEMY_WIDTH: f64 = 64.0;
+const ENEMY_HEIGHT: f64 = 64.0;
+const ENEMY_FPS: f64 = 15.0;
+
+// the enemies walk at this speed, in pixels per second, and wait before
+// turning back at a wall or a ledge
+const ENEMY_MOVE_SPEED: f64 = 64.0;
+const ENEMY_MAX_WAIT_TIME: f64 = 0.5;
+
+// landing on an enemy defeats it and bounces the player up
+const ENEMY_POINTS: u32 = 100;
+const STOMP_BOUNCE_VEL: f32 = -PLAYER_MAX_FALL_SPEED;
+
+// the `idle.png`, `run.png` and optional `die.png` of the enemies of kind `A`
+// are in the `A` directory, and so on
+const ENEMY_SPRITES: &'static str = "assets/sprites/enemies";
+
+// tint of the player sprites drawn for the enemies without sprites of their
+// own
+fn enemy_tint(kind: char) -> (u8, u8, u8) {
+    match kind {
+        'A' => (255, 110, 110),
+        'B' => (120, 255, 120),
+        'C' => (120, 160, 255),
+        'D' => (230, 120, 255),
+        _ => (160, 160, 160),
+    }
+}
+
+#[derive(Clone)]
+struct EnemySprites {
+    idle: AnimatedSprite,
+    run: AnimatedSprite,
+    die: AnimatedSprite,
+}
+
+impl EnemySprites {
+    fn load(phi: &mut Phi, kind: char) -> EnemySprites {
+        let (r, g, b) = enemy_tint(kind);
+        let tinted = |phi: &mut Phi, path: &str| {
+            let sprite = EnemySprites::strip(phi, path);
+            sprite.set_color(r, g, b);
+            sprite
+        };
+
+        let die = format!("{}/{}/die.png", ENEMY_SPRITES, kind);
+        let die = if Path::new(&die).exists() {
+            EnemySprites::strip(phi, &die)
+        } else {
+            tinted(phi, "assets/sprites/player/die.png")
+        };
+
+        let idle = format!("{}/{}/idle.png", ENEMY_SPRITES, kind);
+        let run = format!("{}/{}/run.png", ENEMY_SPRITES, kind);
+        if Path::new(&idle).exists() && Path::new(&run).exists() {
+            return EnemySprites {
+                idle: EnemySprites::strip(phi, &idle),
+                run: EnemySprites::strip(phi, &run),
+                die: die,
+            }
+        }
+
+        debug!("no sprites for the enemies {}, tinting the player ones", kind);
+        EnemySprites {
+            idle: tinted(phi, "assets/sprites/player/idle.png"),
+            run: tinted(phi, "assets/sprites/player/run.png"),
+            die: die,
+        }
+    }
+
+    // an animation whose frames are side by side in the image
+    fn strip(phi: &mut Phi, path: &str) -> AnimatedSprite {
+        let mut builder = SpriteBuilder::new(phi, path);
+        let (width, _) = builder.sheet_size();
+        builder.size(ENEMY_WIDTH, ENEMY_HEIGHT)
+            .fps(ENEMY_FPS)
+            .count((width / ENEMY_WIDTH).max(1.0) as usize)
+            .finalize()
+    }
+}
+
+/// Walks along its platform and waits before turning back at the walls and
+/// the ledges, touching it kills the player unless landing on it from above.
+#[derive(Clone)]
+struct Enemy {
+    kind: char,
+
+    // where the feet of the enemy were at the start
+    home: glm::Vector2<f64>,
+
+    // the die animation plays once the player landed on the enemy, which is
+    // gone when it is over
+    defeated: bool,
+    gone: bool,
+    pos: glm::Vector2<f64>,
+    vel_y: f32,
+    direction: PlayerDirection,
+
+    // waiting before turning back, in seconds
+    wait_time: f64,
+    moving: bool,
+    on_ground: bool,
+    previous_bottom: f32,
+
+    sprites: EnemySprites,
+    local_bounds: Rectangle,
+}
+
+impl Enemy {
+    fn new(sprites: &EnemySprites, kind: char, feet: glm::Vector2<f64>) -> Enemy {
+        let width = ENEMY_WIDTH * 0.35;
+        let height = ENEMY_HEIGHT * 0.7;
+
+        Enemy {
+            kind: kind,
+            home: feet,
+            defeated: false,
+            gone: false,
+            pos: glm::Vector2::new(feet.x - ENEMY_WIDTH / 2.0, feet.y - ENEMY_HEIGHT),
+            vel_y: 0.0,
+            direction: PlayerDirection::Left,
+            wait_time: 0.0,
+            moving: false,
+            on_ground: false,
+            previous_bottom: feet.y as f32,
+            sprites: sprites.clone(),
+            local_bounds: Rectangle {
+                x: (ENEMY_WIDTH - width) / 2.0,
+                y: ENEMY_HEIGHT - height,
+                w: width,
+                h: height,
+            },
+        }
+    }
+
+    fn bounding_rect(&self) -> Rectangle {
+        Rectangle {
+            x: self.pos.x + self.local_bounds.x,
+            y: self.pos.y + self.local_bounds.y,
+            ..self.local_bounds
+        }
+    }
+
+    // a wall in front of the enemy or no floor to walk on, among the tiles
+    // and the moving platforms
+    fn blocked(&self, level: &GameLevel) -> bool {
+        let bounds = self.bounding_rect();
+        let (tile_width, tile_height) = (level.meta.tile_width, level.meta.tile_height);
+
+        let front = match self.direction {
+            PlayerDirection::Left => bounds.x - 1.0,
+            PlayerDirection::Right => bounds.x + bounds.w + 1.0,
+        };
+        let x = glm::floor(front / tile_width) as i32;
+        let top = glm::floor(bounds.y / tile_height) as i32;
+        let feet = glm::floor((bounds.y + bounds.h - 1.0) / tile_height) as i32;
+
+        let bottom = bounds.y + bounds.h;
+        let ahead = |platform: &&MovingPlatform| {
+            platform.bounds.x <= front && front < platform.bounds.x + platform.bounds.w
+        };
+        let wall = level.platforms.iter().filter(&ahead).any(|platform| {
+            platform.collision == TileCollision::Impassable &&
+                platform.bounds.y < bottom && bounds.y < platform.bounds.y + platform.bounds.h
+        });
+        let floor = level.platforms.iter().filter(&ahead).any(|platform| {
+            platform.collision != TileCollision::Passable &&
+                (bottom - platform.bounds.y).abs() <= PLATFORM_TOLERANCE
+        });
+
+        wall || (top..feet + 1).any(|y| level.get_collision(x, y) == TileCollision::Impassable) ||
+            (!floor && level.get_collision(x, feet + 1) == TileCollision::Passable)
+    }
+
+    // the feet of `player` are above the center of the enemy and going down
+    fn stomped_by(&self, player: &Player) -> bool {
+        let bounds = player.bounding_rect();
+        player.vel.y > 0.0 && bounds.y + bounds.h < self.bounding_rect().center().y
+    }
+
+    fn defeat(&mut self) {
+        self.defeated = true;
+        self.sprites.die.reset();
+    }
+
+    fn update(&mut self, level: &GameLevel, elapsed: f64) {
+        if self.defeated {
+            self.gone = self.sprites.die.add_time_once(elapsed);
+            return
+        }
+
+        // ride the moving platform under the enemy
+        if self.on_ground {
+            self.pos = self.pos + level.carried(self.bounding_rect());
+        }
+
+        let gravity = level.meta.gravity.unwrap_or(PLAYER_GRAVITY_ACCEL);
+        self.vel_y = (self.vel_y + gravity * elapsed as f32).min(PLAYER_MAX_FALL_SPEED);
+
+        self.moving = false;
+        let mut dx = 0.0;
+        if self.wait_time > 0.0 {
+            self.wait_time -= elapsed;
+            if self.wait_time <= 0.0 {
+                self.direction = match self.direction {
+                    PlayerDirection::Left => PlayerDirection::Right,
+                    PlayerDirection::Right => PlayerDirection::Left,
+                };
+            }
+        } else if self.on_ground && self.blocked(level) {
+            self.wait_time = ENEMY_MAX_WAIT_TIME;
+        } else {
+            self.moving = true;
+            dx = match self.direction {
+                PlayerDirection::Left => -ENEMY_MOVE_SPEED * elapsed,
+                PlayerDirection::Right => ENEMY_MOVE_SPEED * elapsed,
+            };
+        }
+
+        let old_position = self.pos;
+        self.pos.x += dx;
+        self.pos.y += self.vel_y as f64 * elapsed;
+
+        let (offset, on_ground) = level.collide(self.bounding_rect(), self.previous_bottom);
+        self.pos = self.pos + offset;
+        self.on_ground = on_ground;
+        self.previous_bottom = (self.pos.y + ENEMY_HEIGHT) as f32;
+        if level.crushed(self.bounding_rect()) {
+            self.defeat();
+            return
+        }
+
+        if old_position.y == self.pos.y {
+            self.vel_y = 0.0;
+        }
+
+        if self.moving {
+            self.sprites.run.add_time(elapsed);
+        } else {
+            self.sprites.idle.add_time(elapsed);
+        }
+    }
+
+    fn render(&self, phi: &mut Phi) {
+        let sprite = if self.defeated {
+            &self.sprites.die
+        } else if self.moving {
+            &self.sprites.run
+        } else {
+            &self.sprites.idle
+        };
+        let fx = match self.direction {
+            PlayerDirection::Left => RenderFx::None,
+            PlayerDirection::Right => RenderFx::FlipX,
+        };
+        let rect = Rectangle {
+            x: self.pos.x, y: self.pos.y,
+            w: ENEMY_WIDTH, h: ENEMY_HEIGHT,
+        }.to_sdl();
+        sprite.render(&mut phi.renderer, &rect, fx);
+    }
+}
+
+// how far, in pixels, from the top of a moving platform something still
+// stands on it, the platform moved since it landed
+const PLATFORM_TOLERANCE: f64 = 1.0;
+
+/// A row of tiles moving along the path of a `[platform]` section, which
+/// collides like its tiles and carries what stands on it.
+struct MovingPlatform {
+    tiles: Vec<Tile>,
+    collision: TileCollision,
+    bounds: Rectangle,
+
+    // how far the platform moved on the last frame
+    delta: glm::Vector2<f64>,
+
+    // the top left corners of the platform at the waypoints, the index of
+    // the one it goes to and whether it goes back along the path
+    waypoints: Vec<glm::Vector2<f64>>,
+    next: usize,
+    backwards: bool,
+    looped: bool,
+    speed: f64,
+}
+
+impl MovingPlatform {
+    fn new(meta: &LevelMeta, def: &PlatformDef, tiles: Vec<Tile>,
+           collision: TileCollision) -> MovingPlatform {
+        let waypoints: Vec<glm::Vector2<f64>> = def.path.iter()
+            .map(|&(x, y)| glm::Vector2::new(x as f64 * meta.tile_width, y as f64 * meta.tile_height))
+            .collect();
+
+        MovingPlatform {
+            tiles: tiles,
+            collision: collision,
+            bounds: Rectangle {
+                x: waypoints[0].x,
+                y: waypoints[0].y,
+                w: def.width as f64 * meta.tile_width,
+                h: meta.tile_height,
+            },
+            delta: glm::Vector2::new(0.0, 0.0),
+            waypoints: waypoints,
+            next: 1,
+            backwards: false,
+            looped: def.looped,
+            speed: def.speed,
+        }
+    }
+
+    fn previous_bounds(&self) -> Rectangle {
+        Rectangle {
+            x: self.bounds.x - self.delta.x,
+            y: self.bounds.y - self.delta.y,
+            ..self.bounds
+        }
+    }
+
+    // the waypoint following the one just reached
+    fn turn(&mut self) {
+        let last = self.waypoints.len() - 1;
+        if self.looped {
+            self.next = if self.next == last { 0 } else { self.next + 1 };
+        } else if self.backwards {
+            self.backwards = self.next > 0;
+            self.next = if self.backwards { self.next - 1 } else { 1 };
+        } else {
+            self.backwards = self.next == last;
+            self.next = if self.backwards { last - 1 } else { self.next + 1 };
+        }
+    }
+
+    fn update(&mut self, elapsed: f64) {
+        let start = glm::Vector2::new(self.bounds.x, self.bounds.y);
+        let mut pos = start;
+
+        // a long frame may take the platform past some waypoints
+        let mut distance = self.speed * elapsed;
+        while distance > 0.0 {
+            let to_next = self.waypoints[self.next] - pos;
+            let length = glm::length(to_next);
+            if length > distance {
+                pos = pos + to_next * (distance / length);
+                break;
+            }
+
+            pos = self.waypoints[self.next];
+            distance -= length;
+            self.turn();
+        }
+
+        self.bounds.x = pos.x;
+        self.bounds.y = pos.y;
+        self.delta = pos - start;
+    }
+
+    fn render(&self, phi: &mut Phi) {
+        let mut rect = Rectangle {
+            w: self.bounds.w / self.tiles.len() as f64,
+            ..self.bounds
+        };
+        for tile in &self.tiles {
+            tile.render(&mut phi.renderer, &rect.to_sdl(), RenderFx::None);
+            rect.x += rect.w;
+        }
+    }
+}
+
+// the flag of a checkpoint, raised once the player touched it
+const CHECKPOINT_SPRITE: &'static str = "assets/tiles/checkpoint.png";
+const CHECKPOINT_ACTIVE_SPRITE: &'static str = "assets/tiles/checkpoint-active.png";
+
+/// A tile where the player respawns after touching it.
+struct Checkpoint {
+    bounds: Rectangle,
+
+    // where the player stands when respawning
+    spawn: glm::Vector2<f64>,
+    active: bool,
+    sprite: Sprite,
+    active_sprite: Sprite,
+}
+
+impl Checkpoint {
+    fn render(&self, phi: &mut Phi) {
+        let sprite = if self.active { &self.active_sprite } else { &self.sprite };
+        phi.renderer.copy_sprite(sprite, &self.bounds.to_sdl(), RenderFx::None);
+    }
+}
+
 const GEM_WIDTH: f64 = 32.0;
 const GEM_HEIGHT: f64 = 32.0;
 
+const GEM_POINTS: u32 = 30;
+
+// the collected gems fly up while fading out
+const COLLECT_TIME: f64 = 0.4;
+const COLLECT_RISE: f64 = 40.0;
+
+#[derive(Clone)]
 struct Gem {
     sprite: Sprite,
     origin: glm::Vector2<f64>,
@@ -486,10 +1647,12 @@ struct Gem {
     pos: glm::Vector2<f64>,
     time: f64,
     bounce: f64,
+    radius: f64,
+    points: u32,
 }
 
 impl Gem {
-    fn new<'a>(sprite: &'a Sprite, pos: glm::Vector2<f64>) -> Gem {
+    fn new<'a>(sprite: &'a Sprite, pos: glm::Vector2<f64>, radius: f64) -> Gem {
         let sprite = sprite.clone();
         let (width, height) = sprite.size();
         let origin = glm::Vector2::new(width / 2.0, height / 2.0);
@@ -500,12 +1663,19 @@ impl Gem {
             pos: pos,
             time: pos.x * 0.75,
             bounce: 0.0,
+            radius: radius,
+            points: GEM_POINTS,
         }
     }
 
-    // fn bounding_circle(&self) -> Circle<f64> {
-    //     Circle { position: self.pos, TILE_WIDTH / 3.0f }
-    // }
+    // the circle follows the bounce of the gem
+    fn bounding_circle(&self) -> Circle {
+        Circle {
+            center: glm::Vector2::new(self.pos.x + self.origin.x,
+                                      self.pos.y + self.origin.y + self.bounce),
+            radius: self.radius,
+        }
+    }
 
     fn update(mut self: Box<Self>, phi: &mut Phi, elapsed: f64) -> Option<Box<Gem>> {
         use std::f64;
@@ -528,15 +1698,244 @@ impl Gem {
     }
 }
 
+struct CollectedGem {
+    sprite: Sprite,
+    pos: glm::Vector2<f64>,
+    time: f64,
+}
+
+impl CollectedGem {
+    fn new(gem: &Gem) -> CollectedGem {
+        CollectedGem {
+            sprite: gem.sprite.clone(),
+            pos: glm::Vector2::new(gem.pos.x, gem.pos.y + gem.bounce),
+            time: 0.0,
+        }
+    }
+
+    // `None` once the effect is over
+    fn update(mut self: Box<Self>, elapsed: f64) -> Option<Box<CollectedGem>> {
+        self.time += elapsed;
+        if self.time < COLLECT_TIME { Some(self) } else { None }
+    }
+
+    fn render(&self, phi: &mut Phi) {
+        let t = self.time / COLLECT_TIME;
+        let rect = Rectangle {
+            x: self.pos.x,
+            y: self.pos.y - COLLECT_RISE * t,
+            w: GEM_WIDTH,
+            h: GEM_HEIGHT,
+        }.to_sdl();
+
+        // the texture is shared with the other gems
+        self.sprite.set_alpha((255.0 * (1.0 - t)) as u8);
+        self.sprite.render(&mut phi.renderer, &rect, RenderFx::None);
+        self.sprite.set_alpha(255);
+    }
+}
+
+enum ReplayState {
+    Off,
+
+    // the input of every frame is appended to the replay, which is saved to
+    // the path when the view goes away
+    Recording(Replay, String),
+
+    // the input comes from the replay, the indices are the next frame to
+    // play and the next seed to build a level with
+    Playing(Replay, usize, usize),
+}
+
 pub struct GameView {
+    config: Rc<Config>,
     level: GameLevel,
+    replay: ReplayState,
+
+    campaign: Campaign,
+    session: Session,
+
+    // the editor to go back to when play-testing
+    editor: Option<Box<EditorView>>,
+
+    hud: Hud,
 }
 
 impl GameView {
-    pub fn new(phi: &mut Phi) -> GameView {
-        GameView {
-            level: GameLevel::load(phi, "assets/level-0.txt"),
+    pub fn new(phi: &mut Phi, config: Rc<Config>) -> GameView {
+        info!("starting a new game");
+        let campaign = Campaign::load().unwrap_or_else(|e| {
+            error!("cannot load the campaign: {}", e);
+            panic!("Cannot load the campaign: {}", e)
+        });
+        debug!("campaign of {} levels", campaign.levels.len());
+
+        let session = Session::new(config.seed, config.lives);
+        let replay = match config.record {
+            Some(ref path) => {
+                info!("recording the input to {}", path);
+                let replay = Replay::new(&campaign.levels[0], session.score, session.lives);
+                ReplayState::Recording(replay, path.clone())
+            },
+            None => ReplayState::Off,
+        };
+        GameView::start(phi, config, campaign, session, replay, None)
+    }
+
+    pub fn replay(phi: &mut Phi, config: Rc<Config>, replay: Replay) -> GameView {
+        info!("playing back {} frames of {}", replay.frames.len(), replay.level);
+
+        // the recording goes on with the levels of the campaign, if any
+        let mut campaign = Campaign::load().unwrap_or_else(|e| {
+            warn!("{}, playing back {} only", e, replay.level);
+            Campaign::single(&replay.level)
+        });
+
+        // the game goes on with the score and the lives it was recorded with,
+        // whatever `--lives` says
+        let mut session = Session::resume(None, replay.score, replay.lives);
+        session.level = campaign.index_of(&replay.level);
+
+        GameView::start(phi, config, campaign, session, ReplayState::Playing(replay, 0, 0), None)
+    }
+
+    /// Play the level being edited, escape goes back to `editor`.
+    pub fn playtest(phi: &mut Phi, config: Rc<Config>, path: &str, file: LevelFile,
+                    editor: Box<EditorView>) -> GameView {
+        ::crash::set_view("game (play-test)");
+        ::crash::set_level(Some(path));
+        let mut view = GameView {
+            level: GameLevel::from_file(phi, path, file, config.seed),
+            session: Session::new(None, config.lives),
+            config: config,
+            replay: ReplayState::Off,
+            campaign: Campaign::single(path),
+            editor: Some(editor),
+            hud: Hud::new(),
+        };
+        view.update_hud(phi);
+        view
+    }
+
+    // play the level of the campaign where the session is
+    fn start(phi: &mut Phi, config: Rc<Config>, campaign: Campaign, session: Session,
+             mut replay: ReplayState, editor: Option<Box<EditorView>>) -> GameView {
+        ::crash::set_view(match replay {
+            ReplayState::Playing(..) => "game (replay)",
+            _ => "game",
+        });
+
+        info!("level {} of {}, score {}, lives {}",
+              session.level + 1, campaign.levels.len(), session.score, session.lives);
+
+        // the levels are played back with the seeds they were recorded with,
+        // which may be their own
+        let seed = match replay {
+            ReplayState::Playing(ref replay, _, ref mut next) => {
+                let seed = replay.seeds.get(*next).cloned();
+                if seed.is_none() {
+                    warn!("the replay has no seed for level {}", session.level + 1);
+                }
+                *next += 1;
+                seed
+            },
+            _ => session.level_seed(),
+        };
+        let level = GameLevel::load(phi, &campaign.levels[session.level], seed);
+        if let ReplayState::Recording(ref mut replay, _) = replay {
+            replay.seeds.push(level.seed);
+        }
+
+        let mut view = GameView {
+            config: config,
+            level: level,
+            replay: replay,
+            campaign: campaign,
+            session: session,
+            editor: editor,
+            hud: Hud::new(),
+        };
+        view.update_hud(phi);
+        view
+    }
+
+    fn update_hud(&mut self, phi: &mut Phi) {
+        let level = &self.level;
+        self.hud.update(phi, &HudInfo {
+            score: self.session.score + level.score,
+            time_left: level.time_left,
+            gems: level.total_gems - level.gems.len(),
+            total_gems: level.total_gems,
+            lives: self.session.lives,
+        });
+    }
+
+    // the level is complete: go on with the next one, the victory screen
+    // after the last one or back to the editor after a play-test
+    fn advance(mut self: Box<Self>, phi: &mut Phi) -> ViewAction {
+        if let Some(editor) = self.editor.take() {
+            info!("play-test complete, back to the editor");
+            ::crash::set_view("editor");
+            return ViewAction::Render(editor)
+        }
+
+        let bonus = self.level.time_bonus();
+        self.session.score += self.level.score + bonus;
+        info!("level score {}, time bonus {}, total {}",
+              self.level.score, bonus, self.session.score);
+
+        let next = match self.level.meta.next {
+            Some(ref path) => self.campaign.index_of(path),
+            None => self.session.level + 1,
+        };
+        info!("level {} complete", self.session.level + 1);
+
+        if next >= self.campaign.levels.len() {
+            info!("campaign complete, final score {}", self.session.score);
+            return ViewAction::Render(Box::new(
+                VictoryView::new(phi, self.config.clone(), &self.session)))
+        }
+
+        // the replay moves on to the next view, which saves it
+        let replay = ::std::mem::replace(&mut self.replay, ReplayState::Off);
+        let mut session = self.session.clone();
+        session.level = next;
+
+        ViewAction::Render(Box::new(GameView::start(
+            phi, self.config.clone(), self.campaign.clone(), session, replay, None)))
+    }
+
+    // the level-complete screen, with the points of the level
+    fn show_tally(&mut self, phi: &mut Phi) {
+        let level = &self.level;
+        let seconds = level.time_left.ceil() as u32;
+        let gems = level.total_gems - level.gems.len();
+        let total = self.session.score + level.score + level.time_bonus();
+
+        let gems = format!("Gems: {}/{}   Points: {}", gems, level.total_gems, level.score);
+        let time = format!("Time left: {}:{:02}   Bonus: {}", seconds / 60, seconds % 60,
+                           level.time_bonus());
+        let score = format!("Score: {}", total);
+        self.hud.set_message(phi, &["Level complete!", &gems, &time, &score,
+                                    "Press space to continue or return to retry"]);
+    }
+
+    // play the level again from the start, with the session it started with
+    fn restart(mut self: Box<Self>, phi: &mut Phi) -> ViewAction {
+        info!("restarting level {}", self.session.level + 1);
+
+        // a play-test goes on with the level being edited, which may not be
+        // saved
+        if let Some(editor) = self.editor.take() {
+            let path = self.campaign.levels[self.session.level].clone();
+            let file = self.level.source.take().expect("the editor only plays text levels");
+            return ViewAction::Render(Box::new(
+                GameView::playtest(phi, self.config.clone(), &path, file, editor)))
         }
+
+        let replay = ::std::mem::replace(&mut self.replay, ReplayState::Off);
+        ViewAction::Render(Box::new(GameView::start(
+            phi, self.config.clone(), self.campaign.clone(), self.session.clone(), replay, None)))
     }
 }
 
@@ -549,13 +1948,96 @@ impl View for GameView {
 
         // check if the player pressed escape
         if phi.events.now.key_escape == Some(true) {
+            if let Some(editor) = self.editor.take() {
+                info!("play-test over, back to the editor");
+                ::crash::set_view("editor");
+                return ViewAction::Render(editor)
+            }
+
+            info!("game aborted, back to the menu");
             return ViewAction::Render(Box::new(
-                ::views::menu::MenuView::new(phi)))
+                ::views::menu::MenuView::new(phi, self.config.clone())))
+        }
+
+        // record the input or replace it with the recorded one
+        let elapsed = match self.replay {
+            ReplayState::Off => elapsed,
+            ReplayState::Recording(ref mut replay, _) => {
+                replay.frames.push(Frame {
+                    elapsed: elapsed,
+                    keys: phi.events.key_state(),
+                });
+                elapsed
+            },
+            ReplayState::Playing(ref replay, ref mut next, _) => {
+                match replay.frames.get(*next) {
+                    Some(frame) => {
+                        phi.events.set_key_state(&frame.keys);
+                        *next += 1;
+                        frame.elapsed
+                    },
+                    None => {
+                        info!("playback finished, back to the menu");
+                        return ViewAction::Render(Box::new(
+                            ::views::menu::MenuView::new(phi, self.config.clone())))
+                    }
+                }
+            }
+        };
+
+        ::crash::push_frame(elapsed, &phi.events.key_state());
+
+        // save the level as built, its variants are drawn again from the
+        // seed in the file name
+        if DEBUG && phi.events.now.key_f9 == Some(true) {
+            let path = format!("level-{}.txt", self.level.seed);
+            match self.level.save(&path) {
+                Ok(()) => info!("saved the level to {}", path),
+                Err(e) => error!("cannot save the level to {}: {}", path, e),
+            }
         }
 
         // update the player
         self.level.update(phi, elapsed);
 
+        let score = self.session.score + self.level.score;
+        let new_lives = self.session.earn_lives(score);
+        if new_lives > 0 {
+            info!("{} extra lives at {} points, {} lives", new_lives, score, self.session.lives);
+        }
+
+        // a death costs a life, the game is over after the last one; the
+        // play-tests go on whatever happens
+        if self.level.dead {
+            if self.editor.is_none() {
+                self.session.lives -= 1;
+                info!("{} lives left", self.session.lives);
+            }
+            if self.session.lives == 0 {
+                self.session.score = score;
+                info!("game over, final score {}", self.session.score);
+                return ViewAction::Render(Box::new(
+                    GameOverView::new(phi, self.config.clone(), &self.session)))
+            }
+            self.level.respawn();
+        }
+
+        if self.level.complete {
+            if phi.events.now.key_space == Some(true) {
+                return self.advance(phi)
+            }
+            if phi.events.now.key_return == Some(true) {
+                return self.restart(phi)
+            }
+            self.show_tally(phi);
+        } else if self.level.time_up() {
+            if phi.events.now.key_space == Some(true) {
+                return self.restart(phi)
+            }
+            self.hud.set_message(phi, &["Time's up!", "Press space to try again"]);
+        }
+
+        self.update_hud(phi);
         ViewAction::Render(self)
     }
 
@@ -566,5 +2048,166 @@ impl View for GameView {
 
         // Draw the player
         self.level.render(phi);
+
+        // the HUD is drawn over the level
+        self.hud.render(phi);
+    }
+}
+
+impl Drop for GameView {
+    fn drop(&mut self) {
+        if let ReplayState::Recording(ref replay, ref path) = self.replay {
+            match replay.save(path) {
+                Ok(()) => info!("saved {} frames to {}", replay.frames.len(), path),
+                Err(e) => error!("cannot save the replay to {}: {}", path, e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GameLevel, ENEMY_POINTS, PLAYER_HEIGHT, PLAYER_WIDTH};
+    use level::file::LevelFile;
+    use phi;
+    use replay::Replay;
+
+    use std::env;
+    use std::fs;
+    use std::io::Read;
+
+    // the input recorded on the first level, played back frame by frame,
+    // must move the player and collect the gems the same way every time
+    #[test]
+    fn replay_moves_the_player_the_same_way() {
+        let replay = Replay::load("tests/replays/level-0.txt").unwrap();
+
+        phi::headless(|phi| {
+            let mut level = GameLevel::load(phi, &replay.level, Some(replay.seeds[0]));
+            for frame in &replay.frames {
+                phi.events.set_key_state(&frame.keys);
+                level.update(phi, frame.elapsed);
+            }
+
+            let player = level.player.borrow();
+            assert_eq!((player.pos.x.round(), player.pos.y.round()), (581.0, 288.0));
+            assert_eq!(level.score, 60);
+        });
+    }
+
+    // saving a loaded level must give back its file, the variants of the
+    // levels without a `[variants]` section are not recorded
+    #[test]
+    fn saved_levels_are_unchanged() {
+        let saved = env::temp_dir().join("platform-rs-saved-level.txt");
+        let saved = saved.to_str().unwrap();
+
+        phi::headless(|phi| {
+            for path in &["assets/level-0.txt", "assets/level-1.txt"] {
+                GameLevel::load(phi, path, Some(1)).save(saved).unwrap();
+                assert_eq!(read(saved), read(path), "{} changed when saved", path);
+            }
+
+            GameLevel::load(phi, "tests/maps/small.json", Some(1)).save(saved).unwrap();
+            assert!(read(saved).contains(".1--BX"), "the Tiled map is not saved as text");
+        });
+        fs::remove_file(saved).unwrap();
+    }
+
+    // the loader refuses what the validator reports as an error
+    #[test]
+    #[should_panic(expected = "missing exit")]
+    fn levels_with_errors_are_not_loaded() {
+        let file = LevelFile::parse("[tiles]\n.1....\n######\n").unwrap();
+        phi::headless(|phi| {
+            GameLevel::from_file(phi, "no exit", file, Some(1));
+        });
+    }
+
+    // the time left is the one at the start or at the last checkpoint
+    #[test]
+    fn respawning_restores_the_time() {
+        let file = LevelFile::parse("[level]\ntime = 60\n\n[tiles]\n..........\n\
+                                     .1..K...X.\n##########\n").unwrap();
+
+        phi::headless(|phi| {
+            let mut level = GameLevel::from_file(phi, "checkpoint", file, Some(1));
+            level.time_left = 5.0;
+            level.respawn();
+            assert_eq!(level.time_left, 60.0);
+
+            let checkpoint = level.checkpoints[0].bounds;
+            level.player.borrow_mut().pos.x = checkpoint.x;
+            level.time_left = 30.0;
+            level.update(phi, 1.0 / 60.0);
+            assert!(level.checkpoints[0].active);
+            let reached = level.time_left;
+
+            level.update(phi, 1.0 / 60.0);
+            level.respawn();
+            assert_eq!(level.time_left, reached);
+        });
+    }
+
+    // landing on two enemies side by side defeats both of them
+    #[test]
+    fn landing_on_two_enemies_defeats_both() {
+        let file = LevelFile::parse("[tiles]\n..........\n..........\n..........\n\
+                                     .1..AA..X.\n##########\n").unwrap();
+
+        phi::headless(|phi| {
+            let mut level = GameLevel::from_file(phi, "two enemies", file, Some(1));
+            let (left, right) = (level.enemies[0].bounding_rect(), level.enemies[1].bounding_rect());
+            {
+                // falling over the gap between the enemies, the feet in both
+                let mut player = level.player.borrow_mut();
+                player.pos.x = (left.x + left.w + right.x) / 2.0 - PLAYER_WIDTH / 2.0;
+                player.pos.y = left.y + 4.0 - PLAYER_HEIGHT;
+                player.vel.y = 200.0;
+            }
+            level.update(phi, 1.0 / 60.0);
+
+            let player = level.player.borrow();
+            assert!(player.alive);
+            assert!(player.vel.y < 0.0, "the player doesn't bounce");
+            assert!(level.enemies.iter().all(|enemy| enemy.defeated));
+            assert_eq!(level.score, 2 * ENEMY_POINTS);
+        });
+    }
+
+    #[test]
+    fn platform_going_up_crushes_the_player_into_the_ceiling() {
+        let file = LevelFile::parse("[tiles]\n##########\n..........\n..........\n\
+                                     ..........\n..........\n..........\n.1......X.\n\
+                                     ##########\n\n[platform]\ntile = #\nwidth = 3\n\
+                                     path = 3,6 3,1\nspeed = 200\n").unwrap();
+
+        phi::headless(|phi| {
+            let mut level = GameLevel::from_file(phi, "crushing platform", file, Some(1));
+            let platform = level.platforms[0].bounds;
+            {
+                let mut player = level.player.borrow_mut();
+                player.pos.x = platform.x + platform.w / 2.0 - PLAYER_WIDTH / 2.0;
+                player.pos.y = platform.y - PLAYER_HEIGHT;
+                player.previous_bottom = platform.y as f32;
+                player.on_ground = true;
+            }
+
+            for _ in 0..120 {
+                level.update(phi, 1.0 / 60.0);
+                if !level.player.borrow().alive {
+                    break;
+                }
+            }
+
+            assert!(!level.player.borrow().alive, "the player went through the ceiling");
+            assert!(level.player.borrow().bounding_rect().y > 0.0);
+        });
+    }
+
+    fn read(path: &str) -> String {
+        let mut text = String::new();
+        fs::File::open(path).and_then(|mut f| f.read_to_string(&mut text)).unwrap();
+        text
     }
 }
@@ -0,0 +1,58 @@
+// src/views/gameover.rs
+
+use config::Config;
+use level::campaign::Session;
+use phi::{Phi, View, ViewAction};
+use sdl2::pixels::Color;
+use std::rc::Rc;
+use views::message::MessageScreen;
+
+/// Shown when the player lost the last life, space starts a new game from
+/// the first level and escape goes back to the menu.
+pub struct GameOverView {
+    config: Rc<Config>,
+    screen: MessageScreen,
+}
+
+impl GameOverView {
+    pub fn new(phi: &mut Phi, config: Rc<Config>, session: &Session) -> GameOverView {
+        ::crash::set_view("game over");
+        ::crash::set_level(None);
+
+        let score = format!("Score: {}", session.score);
+        let screen = MessageScreen::new(phi, Color::RGB(20, 0, 0), &[
+            ("Game over", 64, Color::RGB(220, 40, 40)),
+            (&score, 38, Color::RGB(255, 255, 255)),
+            ("Press space to try again or escape for the menu", 24, Color::RGB(220, 220, 220)),
+        ]);
+
+        GameOverView {
+            config: config,
+            screen: screen,
+        }
+    }
+}
+
+impl View for GameOverView {
+    fn update(self: Box<Self>, phi: &mut Phi, _: f64) -> ViewAction {
+        if phi.events.now.quit {
+            return ViewAction::Quit
+        }
+
+        if phi.events.now.key_space == Some(true) {
+            return ViewAction::Render(Box::new(
+                ::views::game::GameView::new(phi, self.config.clone())))
+        }
+
+        if phi.events.now.key_escape == Some(true) {
+            return ViewAction::Render(Box::new(
+                ::views::menu::MenuView::new(phi, self.config.clone())))
+        }
+
+        ViewAction::Render(self)
+    }
+
+    fn render(&self, phi: &mut Phi) {
+        self.screen.render(phi);
+    }
+}
@@ -0,0 +1,153 @@
+// src/views/hud.rs
+
+use phi::Phi;
+use phi::data::Rectangle;
+use phi::gfx::{CopySprite, RenderFx, Sprite};
+use sdl2::pixels::Color;
+
+// constants
+const FONT_NAME: &'static str = "assets/fonts/belligerent.ttf";
+const FONT_SIZE: u16 = 24;
+const MARGIN: f64 = 10.0;
+const LINE_SPACING: f64 = 2.0;
+const TITLE_SIZE: u16 = 64;
+const HINT_SIZE: u16 = 28;
+
+/// Below this many seconds left the time flashes.
+pub const WARNING_TIME: f64 = 30.0;
+
+/// What the HUD shows.
+pub struct HudInfo {
+    pub score: u32,
+
+    /// seconds left to complete the level
+    pub time_left: f64,
+    pub gems: usize,
+    pub total_gems: usize,
+    pub lives: u32,
+}
+
+// a line of text, rendered again only when it changes
+struct HudLine {
+    text: String,
+    size: u16,
+    color: Color,
+    sprite: Option<Sprite>,
+}
+
+impl HudLine {
+    fn new(size: u16) -> HudLine {
+        HudLine {
+            text: String::new(),
+            size: size,
+            color: Color::RGB(0, 0, 0),
+            sprite: None,
+        }
+    }
+
+    fn set(&mut self, phi: &mut Phi, text: String, color: Color) {
+        if text != self.text || color != self.color {
+            self.sprite = if text.is_empty() {
+                None
+            } else {
+                phi.ttf_str_sprite(&text, FONT_NAME, self.size, color)
+            };
+            self.text = text;
+            self.color = color;
+        }
+    }
+}
+
+/// The score, time, gems and lives drawn over the level.
+///
+/// The HUD is drawn in screen space: the time, score and gems in the top left
+/// corner and the lives in the top right one. A message, like the end of the
+/// level, may be shown in the center of the screen.
+pub struct Hud {
+    time: HudLine,
+    score: HudLine,
+    gems: HudLine,
+    lives: HudLine,
+
+    // the title then the smaller lines below it
+    message: Vec<HudLine>,
+}
+
+impl Hud {
+    pub fn new() -> Hud {
+        Hud {
+            time: HudLine::new(FONT_SIZE),
+            score: HudLine::new(FONT_SIZE),
+            gems: HudLine::new(FONT_SIZE),
+            lives: HudLine::new(FONT_SIZE),
+            message: Vec::new(),
+        }
+    }
+
+    /// Show `lines` in the center of the screen, the first one is the title
+    /// of the message and the following ones are smaller.
+    pub fn set_message(&mut self, phi: &mut Phi, lines: &[&str]) {
+        self.message.truncate(lines.len());
+        while self.message.len() < lines.len() {
+            let size = if self.message.is_empty() { TITLE_SIZE } else { HINT_SIZE };
+            self.message.push(HudLine::new(size));
+        }
+
+        for (i, (line, text)) in self.message.iter_mut().zip(lines).enumerate() {
+            let color = if i == 0 { Color::RGB(255, 220, 60) } else { Color::RGB(255, 255, 255) };
+            line.set(phi, text.to_string(), color);
+        }
+    }
+
+    pub fn update(&mut self, phi: &mut Phi, info: &HudInfo) {
+        let normal = Color::RGB(255, 255, 0);
+
+        // the time flashes red every other second when running low
+        let seconds = info.time_left.max(0.0).ceil() as u32;
+        let color = if info.time_left > WARNING_TIME || seconds % 2 == 0 {
+            normal
+        } else {
+            Color::RGB(255, 0, 0)
+        };
+        self.time.set(phi, format!("TIME: {}:{:02}", seconds / 60, seconds % 60), color);
+
+        self.score.set(phi, format!("SCORE: {}", info.score), normal);
+        self.gems.set(phi, format!("GEMS: {}/{}", info.gems, info.total_gems), normal);
+        self.lives.set(phi, format!("LIVES: {}", info.lives), normal);
+    }
+
+    pub fn render(&self, phi: &mut Phi) {
+        let mut y = MARGIN;
+        for line in &[&self.time, &self.score, &self.gems] {
+            if let Some(ref sprite) = line.sprite {
+                let (w, h) = sprite.size();
+                phi.renderer.copy_sprite(sprite, &Rectangle {
+                    x: MARGIN, y: y, w: w, h: h,
+                }.to_sdl(), RenderFx::None);
+                y += h + LINE_SPACING;
+            }
+        }
+
+        let (win_w, win_h) = phi.output_size();
+        if let Some(ref sprite) = self.lives.sprite {
+            let (w, h) = sprite.size();
+            phi.renderer.copy_sprite(sprite, &Rectangle {
+                x: win_w - w - MARGIN, y: MARGIN, w: w, h: h,
+            }.to_sdl(), RenderFx::None);
+        }
+
+        // the message is centered
+        let sprites: Vec<&Sprite> = self.message.iter()
+            .filter_map(|line| line.sprite.as_ref())
+            .collect();
+        let total_h = sprites.iter().map(|sprite| sprite.size().1 + MARGIN).sum::<f64>() - MARGIN;
+        let mut y = (win_h - total_h) / 2.0;
+        for sprite in sprites {
+            let (w, h) = sprite.size();
+            phi.renderer.copy_sprite(sprite, &Rectangle {
+                x: (win_w - w) / 2.0, y: y, w: w, h: h,
+            }.to_sdl(), RenderFx::None);
+            y += h + MARGIN;
+        }
+    }
+}
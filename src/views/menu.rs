@@ -1,9 +1,11 @@
 // src/views/menu.rs
 
+use config::Config;
 use phi::data::Rectangle;
 use phi::gfx::{CopySprite, RenderFx, Sprite};
 use phi::{Phi, View, ViewAction};
 use sdl2::pixels::Color;
+use std::rc::Rc;
 
 // constants
 const FONT_NAME: &'static str = "assets/fonts/belligerent.ttf";
@@ -46,11 +48,22 @@ pub struct MenuView {
 }
 
 impl MenuView {
-    pub fn new(phi: &mut Phi) -> MenuView {
+    pub fn new(phi: &mut Phi, config: Rc<Config>) -> MenuView {
+        info!("entering the main menu");
+        ::crash::set_view("menu");
+        ::crash::set_level(None);
+        let editor_config = config.clone();
         MenuView {
             actions: vec![
-                Action::new(phi, "New Game", Box::new(|phi| {
-                    ViewAction::Render(Box::new((::views::game::GameView::new(phi))))
+                Action::new(phi, "New Game", Box::new(move |phi| {
+                    ViewAction::Render(Box::new((::views::game::GameView::new(phi, config.clone()))))
+                })),
+
+                Action::new(phi, "Level Editor", Box::new(move |phi| {
+                    let path = editor_config.edit.clone()
+                        .unwrap_or_else(|| ::views::editor::DEFAULT_LEVEL.to_string());
+                    ViewAction::Render(Box::new(
+                        ::views::editor::EditorView::new(phi, editor_config.clone(), &path)))
                 })),
 
                 Action::new(phi, "Quit", Box::new(|_| ViewAction::Quit )),
@@ -65,6 +78,7 @@ impl View for MenuView {
     fn update(mut self: Box<Self>, phi: &mut Phi, _: f64) -> ViewAction {
         // Quit if the player wants to quit or presses 'Escape'
         if phi.events.now.quit || phi.events.now.key_escape == Some(true) {
+            info!("quit requested from the menu");
             return ViewAction::Quit
         }
 
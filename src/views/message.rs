@@ -0,0 +1,54 @@
+// src/views/message.rs
+
+use phi::data::Rectangle;
+use phi::gfx::{CopySprite, RenderFx, Sprite};
+use phi::Phi;
+use sdl2::pixels::Color;
+
+// constants
+const FONT_NAME: &'static str = "assets/fonts/belligerent.ttf";
+const LINE_SPACING: f64 = 20.0;
+
+/// Lines of text centered on a plain background, drawn by the screens
+/// between the games such as the victory and the game over ones.
+pub struct MessageScreen {
+    background: Color,
+
+    // lines of text, from the top
+    lines: Vec<Sprite>,
+}
+
+impl MessageScreen {
+    /// `lines` are the text, the font size and the color of each line, from
+    /// the top.
+    pub fn new(phi: &mut Phi, background: Color, lines: &[(&str, u16, Color)]) -> MessageScreen {
+        MessageScreen {
+            background: background,
+            lines: lines.iter()
+                .filter_map(|&(text, size, color)| phi.ttf_str_sprite(text, FONT_NAME, size, color))
+                .collect(),
+        }
+    }
+
+    pub fn render(&self, phi: &mut Phi) {
+        phi.renderer.set_draw_color(self.background);
+        phi.renderer.clear();
+
+        // center the lines on the screen
+        let (win_w, win_h) = phi.output_size();
+        let total_h = self.lines.iter().map(|l| l.size().1).sum::<f64>() +
+            LINE_SPACING * (self.lines.len() as f64 - 1.0);
+
+        let mut y = (win_h - total_h) / 2.0;
+        for line in &self.lines {
+            let (w, h) = line.size();
+            phi.renderer.copy_sprite(line, &Rectangle {
+                x: (win_w - w) / 2.0,
+                y: y,
+                w: w,
+                h: h,
+            }.to_sdl(), RenderFx::None);
+            y += h + LINE_SPACING;
+        }
+    }
+}
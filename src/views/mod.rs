@@ -1,4 +1,9 @@
 // src/views/mod.rs
 
+pub mod editor;
 pub mod game;
+pub mod gameover;
+pub mod hud;
 pub mod menu;
+pub mod message;
+pub mod victory;
@@ -0,0 +1,53 @@
+// src/views/victory.rs
+
+use config::Config;
+use level::campaign::Session;
+use phi::{Phi, View, ViewAction};
+use sdl2::pixels::Color;
+use std::rc::Rc;
+use views::message::MessageScreen;
+
+/// Shown after the last level of the campaign, space or escape go back to
+/// the menu.
+pub struct VictoryView {
+    config: Rc<Config>,
+    screen: MessageScreen,
+}
+
+impl VictoryView {
+    pub fn new(phi: &mut Phi, config: Rc<Config>, session: &Session) -> VictoryView {
+        ::crash::set_view("victory");
+        ::crash::set_level(None);
+
+        let score = format!("Score: {}", session.score);
+        let screen = MessageScreen::new(phi, Color::RGB(0, 0, 20), &[
+            ("You won!", 64, Color::RGB(255, 220, 60)),
+            (&score, 38, Color::RGB(255, 255, 255)),
+            ("Press space to continue", 24, Color::RGB(220, 220, 220)),
+        ]);
+
+        VictoryView {
+            config: config,
+            screen: screen,
+        }
+    }
+}
+
+impl View for VictoryView {
+    fn update(self: Box<Self>, phi: &mut Phi, _: f64) -> ViewAction {
+        if phi.events.now.quit {
+            return ViewAction::Quit
+        }
+
+        if phi.events.now.key_space == Some(true) || phi.events.now.key_escape == Some(true) {
+            return ViewAction::Render(Box::new(
+                ::views::menu::MenuView::new(phi, self.config.clone())))
+        }
+
+        ViewAction::Render(self)
+    }
+
+    fn render(&self, phi: &mut Phi) {
+        self.screen.render(phi);
+    }
+}
@@ -0,0 +1,86 @@
+// tests/generate.rs
+
+extern crate platform_rs;
+
+use platform_rs::level::generate::{generate, JumpReach, Params};
+use platform_rs::level::physics::PLAYER_GRAVITY_ACCEL;
+use platform_rs::level::tileset::{Tileset, DEFAULT_TILESET};
+use platform_rs::phi::gfx::TileCollision;
+
+// a run of solid tiles of a generated level
+struct Run {
+    x: usize,
+    len: usize,
+    row: usize,
+}
+
+// the runs of solid tiles of the grid, from left to right
+fn runs(tiles: &[String], tileset: &Tileset) -> Vec<Run> {
+    let mut runs = Vec::new();
+    for (row, line) in tiles.iter().enumerate() {
+        let solid: Vec<bool> = line.chars()
+            .map(|c| tileset.get(c).unwrap().collision != TileCollision::Passable)
+            .collect();
+
+        let mut x = 0;
+        while x < solid.len() {
+            if solid[x] {
+                let len = solid[x..].iter().take_while(|&&s| s).count();
+                runs.push(Run { x: x, len: len, row: row });
+                x += len;
+            } else {
+                x += 1;
+            }
+        }
+    }
+    runs.sort_by_key(|run| run.x);
+    runs
+}
+
+// every jump from a platform to the next one must be within the reach of the
+// player, whatever the seed and the difficulty
+#[test]
+fn every_jump_can_be_made() {
+    let tileset = Tileset::load(DEFAULT_TILESET).unwrap();
+
+    for &gravity in &[2000.0, PLAYER_GRAVITY_ACCEL, 5000.0] {
+        for step in 0..5 {
+            let params = Params {
+                difficulty: step as f64 / 4.0,
+                gravity: gravity,
+                ..Params::default()
+            };
+            let reach = JumpReach::new(&params);
+
+            for seed in 0..50 {
+                let file = generate(seed, &params, &tileset).unwrap();
+                let runs = runs(&file.tiles, &tileset);
+                assert!(runs.len() > 1, "seed {}: a single platform", seed);
+
+                for pair in runs.windows(2) {
+                    let (from, to) = (&pair[0], &pair[1]);
+                    let end = from.x + from.len;
+                    assert!(to.x >= end, "seed {}: the platforms at x {} and {} overlap",
+                            seed, from.x, to.x);
+
+                    let gap = to.x - end;
+                    let rise = from.row as i32 - to.row as i32;
+                    match reach.max_gap(rise) {
+                        Some(max_gap) => assert!(
+                            gap <= max_gap,
+                            "seed {}, difficulty {}, gravity {}: gap of {} at x {} with a rise \
+                             of {}, the player jumps over {} at most",
+                            seed, params.difficulty, gravity, gap, end, rise, max_gap),
+                        None => panic!("seed {}, difficulty {}, gravity {}: rise of {} at x {} \
+                                        out of reach", seed, params.difficulty, gravity, rise, end),
+                    }
+                }
+
+                // the start is over the first platform and the exit over the last one
+                let (first, last) = (&runs[0], runs.last().unwrap());
+                assert_eq!(file.tiles[first.row - 1].find('1'), Some(first.x + 1));
+                assert_eq!(file.tiles[last.row - 1].find('X'), Some(last.x + last.len - 1));
+            }
+        }
+    }
+}
@@ -0,0 +1,157 @@
+// tests/level_file.rs
+
+extern crate platform_rs;
+
+use platform_rs::level::file::LevelFile;
+use platform_rs::level::generate::{generate, Params};
+use platform_rs::level::tiled::TiledMap;
+use platform_rs::level::tileset::{Tileset, DEFAULT_TILESET};
+
+use std::fs;
+
+fn write(file: &LevelFile) -> String {
+    let mut text = Vec::new();
+    file.write(&mut text).unwrap();
+    String::from_utf8(text).unwrap()
+}
+
+// writing the parsed text must give it back, and parsing it again the same file
+fn round_trip(name: &str, text: &str) {
+    let file = LevelFile::parse(text).unwrap_or_else(|e| panic!("{}: {}", name, e));
+    let written = write(&file);
+    assert_eq!(written, text, "{} changed when written", name);
+
+    let again = LevelFile::parse(&written).unwrap_or_else(|e| panic!("{}: {}", name, e));
+    assert_eq!(again, file, "{} parsed differently once written", name);
+}
+
+#[test]
+fn levels_are_written_back_unchanged() {
+    let mut levels = 0;
+    for entry in fs::read_dir("assets").unwrap() {
+        let path = entry.unwrap().path();
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        if name.starts_with("level-") && name.ends_with(".txt") {
+            let text = fs::read_to_string(&path).unwrap();
+            round_trip(&name, &text);
+            levels += 1;
+        }
+    }
+    assert!(levels > 0, "no level found in assets");
+}
+
+#[test]
+fn generated_levels_are_written_back_unchanged() {
+    let tileset = Tileset::load(DEFAULT_TILESET).unwrap();
+    for seed in 0..10 {
+        let file = generate(seed, &Params::default(), &tileset).unwrap();
+        round_trip(&format!("generated level {}", seed), &write(&file));
+    }
+}
+
+#[test]
+fn comments_and_blank_lines_are_kept() {
+    round_trip("comments", "\
+[level]
+# the first level
+name = The beginning
+
+# in seconds
+time = 120
+
+
+[tiles]
+.1..X.
+######
+
+[variants]
+......
+012345
+
+[platform lift]
+
+tile = -
+# straight up
+path = 1,1 1,0
+# the end
+");
+}
+
+#[test]
+fn bare_grids_stay_bare() {
+    round_trip("bare grid", ".1..X.\n######\n");
+}
+
+#[test]
+fn tiled_maps_are_converted_to_text() {
+    let map = TiledMap::load("tests/maps/small.json").unwrap();
+    let tileset = Tileset::load(DEFAULT_TILESET).unwrap();
+    let file = map.to_text(&tileset).unwrap();
+
+    assert_eq!(write(&file), "\
+[level]
+name = Small
+
+[tiles]
+...G..
+.1--BX
+######
+");
+}
+
+// the tiles are picked by image, the first tile of the collision when no
+// tile has the image
+#[test]
+fn tiled_tiles_are_converted_by_image() {
+    let map = TiledMap::load("tests/maps/images.json").unwrap();
+    let tileset = Tileset::load(DEFAULT_TILESET).unwrap();
+    let file = map.to_text(&tileset).unwrap();
+
+    assert_eq!(file.tiles, vec!["....", "#~-#"]);
+}
+
+#[test]
+fn layer_variants_are_kept() {
+    let text = "\
+[tiles]
+.1..X.
+######
+
+[foreground grass]
+::....
+......
+
+[variants foreground grass]
+01....
+......
+";
+    round_trip("layer variants", text);
+
+    let file = LevelFile::parse(text).unwrap();
+    assert_eq!(file.layers[0].variant(1, 0), Some(Ok(1)));
+    assert_eq!(file.layers[0].variants_line, 10);
+}
+
+#[test]
+fn layer_variants_need_their_layer() {
+    let e = LevelFile::parse("[tiles]\n.1X\n###\n\n[variants background]\n0..\n").unwrap_err();
+    assert_eq!(e.line, 5);
+}
+
+#[test]
+fn line_endings_are_kept() {
+    round_trip("crlf", "[level]\r\nname = Windows\r\n\r\n[tiles]\r\n.1..X.\r\n######\r\n");
+    round_trip("bare crlf grid", ".1..X.\r\n######\r\n");
+}
+
+#[test]
+fn tile_sizes_must_be_positive() {
+    for size in &["0", "-32"] {
+        let text = format!("[level]\nname = Flat\ntile_height = {}\n\n[tiles]\n.1X\n###\n", size);
+        let e = LevelFile::parse(&text).unwrap().meta().unwrap_err();
+        assert_eq!(e.line, 3);
+    }
+    let e = LevelFile::parse("[level]\ntile_width = 0\n\n[tiles]\n.1X\n###\n")
+        .unwrap().meta().unwrap_err();
+    assert_eq!(e.line, 2);
+}
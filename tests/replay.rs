@@ -0,0 +1,36 @@
+// tests/replay.rs
+
+extern crate platform_rs;
+
+use platform_rs::level::campaign::START_LIVES;
+use platform_rs::replay::Replay;
+
+use std::env;
+use std::fs;
+
+#[test]
+fn the_header_is_saved_and_loaded() {
+    let path = env::temp_dir().join("platform-rs-replay.txt");
+    let path = path.to_str().unwrap();
+
+    let mut replay = Replay::new("assets/level-1.txt", 1250, 2);
+    replay.seeds = vec![7, 8];
+    replay.save(path).unwrap();
+    let loaded = Replay::load(path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    assert_eq!(loaded.seeds, vec![7, 8]);
+    assert_eq!(loaded.level, "assets/level-1.txt");
+    assert_eq!((loaded.score, loaded.lives), (1250, 2));
+}
+
+#[test]
+fn the_score_and_the_lives_may_be_missing() {
+    let path = env::temp_dir().join("platform-rs-old-replay.txt");
+    let path = path.to_str().unwrap();
+    fs::write(path, "seed 1\nlevel assets/level-0.txt\n").unwrap();
+    let loaded = Replay::load(path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    assert_eq!((loaded.score, loaded.lives), (0, START_LIVES));
+}
@@ -0,0 +1,147 @@
+// tests/validate.rs
+
+extern crate platform_rs;
+
+use platform_rs::level::file::LevelFile;
+use platform_rs::level::tileset::{Tileset, DEFAULT_TILESET};
+use platform_rs::level::validate::{validate, validate_path, Diagnostic, Severity};
+
+use std::env;
+use std::fs;
+use std::process::Command;
+
+fn check(text: &str) -> Vec<Diagnostic> {
+    let file = LevelFile::parse(text).unwrap();
+    validate(&file, &Tileset::load(DEFAULT_TILESET).unwrap())
+}
+
+// the severity, line and column of the only diagnostic of `text`
+fn only(text: &str) -> (Severity, usize, usize) {
+    let diagnostics = check(text);
+    assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+    let d = &diagnostics[0];
+    (d.severity, d.line, d.column)
+}
+
+// write `text` to a file of the temporary directory and give its path
+fn temp_file(name: &str, text: &str) -> String {
+    let path = env::temp_dir().join(name);
+    fs::write(&path, text).unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+#[test]
+fn valid_levels_have_no_diagnostics() {
+    assert!(check("[level]\nname = Fine\n\n[tiles]\n.1..X.\n######\n").is_empty());
+    for path in &["assets/level-0.txt", "assets/level-1.txt", "tests/maps/small.json"] {
+        assert!(validate_path(path).is_empty(), "{}: {:?}", path, validate_path(path));
+    }
+}
+
+#[test]
+fn ragged_rows() {
+    assert_eq!(only("[level]\nname = Ragged\n\n[tiles]\n.1..X.\n####\n"),
+               (Severity::Error, 6, 5));
+    assert_eq!(only(".1..X.\n#######\n"), (Severity::Error, 2, 7));
+}
+
+#[test]
+fn unknown_tiles() {
+    assert_eq!(only("[tiles]\n.1..X.\n##?###\n"), (Severity::Error, 3, 3));
+}
+
+#[test]
+fn missing_start() {
+    assert_eq!(only("[level]\nname = Nowhere\n\n[tiles]\n....X.\n######\n"),
+               (Severity::Error, 5, 0));
+}
+
+#[test]
+fn missing_exit() {
+    assert_eq!(only("[tiles]\n.1....\n######\n"), (Severity::Error, 2, 0));
+}
+
+#[test]
+fn duplicate_start() {
+    let diagnostics = check("[tiles]\n.1..X.\n######\n...1..\n######\n");
+    assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+    assert_eq!((diagnostics[0].severity, diagnostics[0].line, diagnostics[0].column),
+               (Severity::Error, 4, 4));
+    assert!(diagnostics[0].message.contains("2:2"), "{}", diagnostics[0].message);
+}
+
+#[test]
+fn duplicate_exit() {
+    assert_eq!(only("[tiles]\n.1..XX\n######\n"), (Severity::Error, 2, 6));
+}
+
+#[test]
+fn start_without_floor() {
+    assert_eq!(only("[tiles]\n.1..X.\n#.####\n"), (Severity::Warning, 2, 2));
+}
+
+#[test]
+fn tiled_maps() {
+    let map = |objects: &str| format!(r#"{{
+  "width": 2, "height": 2, "tilewidth": 40, "tileheight": 32,
+  "orientation": "orthogonal", "infinite": false, "tilesets": [],
+  "layers": [
+    {{"type": "tilelayer", "name": "ground", "visible": true, "data": [0, 0, 0, 0]}},
+    {{"type": "objectgroup", "name": "objects", "objects": [{}]}}
+  ]
+}}"#, objects);
+    let start = r#"{"name": "start", "type": "start", "x": 0, "y": 0, "width": 40, "height": 32}"#;
+    let exit = r#"{"name": "exit", "type": "exit", "x": 40, "y": 0, "width": 40, "height": 32}"#;
+
+    let path = temp_file("platform-rs-validate.json", &map(&format!("{}, {}", start, exit)));
+    assert!(validate_path(&path).is_empty());
+
+    fs::write(&path, map(&format!("{}, {}, {}", start, start, exit))).unwrap();
+    let diagnostics = validate_path(&path);
+    assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+    assert_eq!(diagnostics[0].severity, Severity::Error);
+    assert_eq!(diagnostics[0].message, "2 start objects, expected one");
+
+    fs::write(&path, map(start)).unwrap();
+    let diagnostics = validate_path(&path);
+    assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+    assert_eq!(diagnostics[0].message, "missing exit object");
+
+    let platform = r#"{"name": "lift", "type": "platform", "x": 0, "y": 0, "width": 40, "height": 32}"#;
+    fs::write(&path, map(&format!("{}, {}, {}", start, exit, platform))).unwrap();
+    let diagnostics = validate_path(&path);
+    assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+    assert_eq!(diagnostics[0].severity, Severity::Warning);
+
+    fs::remove_file(&path).unwrap();
+}
+
+// the command fails on errors only, and with 2 on a wrong usage
+#[test]
+fn command_exit_code() {
+    let run = |args: &[&str]| Command::new(env!("CARGO_BIN_EXE_validate-level"))
+        .args(args)
+        .output()
+        .unwrap();
+
+    let valid = run(&["assets/level-0.txt", "tests/maps/small.json"]);
+    assert_eq!(valid.status.code(), Some(0));
+    assert!(valid.stdout.is_empty());
+
+    let warned = temp_file("platform-rs-warned.txt", "[tiles]\n.1..X.\n#.####\n");
+    let output = run(&[&warned]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8_lossy(&output.stdout),
+               format!("{}:2:2: warning: the start point has no floor underneath\n", warned));
+
+    let broken = temp_file("platform-rs-broken.txt", "[tiles]\n.1....\n######\n");
+    let output = run(&[&warned, &broken]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8_lossy(&output.stdout).lines().last(),
+               Some(format!("{}:2: error: missing exit", broken).as_str()));
+
+    assert_eq!(run(&[]).status.code(), Some(2));
+
+    fs::remove_file(&warned).unwrap();
+    fs::remove_file(&broken).unwrap();
+}